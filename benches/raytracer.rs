@@ -0,0 +1,68 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use image::RgbaImage;
+use rust_wgpu_lib::{
+    camera::Camera,
+    raytracer::{
+        cast_ray, render_image,
+        scene::{Aabb, Scene, Sphere},
+        RenderSettings,
+    },
+};
+
+const SEED: u64 = 42;
+const BOUNDS: Aabb = Aabb {
+    min: glam::Vec3::new(-5.0, -5.0, -10.0),
+    max: glam::Vec3::new(5.0, 5.0, 0.0),
+};
+
+fn simple_scene() -> Scene {
+    let mut scene = Scene::default();
+    scene.add_sphere(Sphere {
+        albedo: glam::Vec3::new(1.0, 0.0, 1.0),
+        radius: 0.5,
+        position: glam::Vec3::ZERO,
+        ..Default::default()
+    });
+    scene.add_sphere(Sphere {
+        albedo: glam::Vec3::new(0.2, 0.3, 1.0),
+        radius: 1.5,
+        position: glam::Vec3::new(1.0, 0.0, -5.0),
+        ..Default::default()
+    });
+    scene
+}
+
+fn bench_cast_ray(c: &mut Criterion) {
+    let camera = Camera::default();
+    let settings = RenderSettings::default();
+    let ray = camera.ray_through_pixel(32, 32, 64, 64);
+
+    let scene = simple_scene();
+    c.bench_function("cast_ray simple scene", |b| {
+        b.iter(|| cast_ray(black_box(&scene), black_box(&ray), &settings))
+    });
+
+    let scene = Scene::random_spheres(100, SEED, BOUNDS);
+    c.bench_function("cast_ray 100 spheres", |b| {
+        b.iter(|| cast_ray(black_box(&scene), black_box(&ray), &settings))
+    });
+}
+
+fn bench_render_image(c: &mut Criterion) {
+    let camera = Camera::default();
+    let settings = RenderSettings::default();
+    let mut img = RgbaImage::new(64, 64);
+
+    let scene = simple_scene();
+    c.bench_function("render_image 64x64 simple scene", |b| {
+        b.iter(|| render_image(&mut img, black_box(&scene), &camera, &settings))
+    });
+
+    let scene = Scene::random_spheres(100, SEED, BOUNDS);
+    c.bench_function("render_image 64x64 100 spheres", |b| {
+        b.iter(|| render_image(&mut img, black_box(&scene), &camera, &settings))
+    });
+}
+
+criterion_group!(benches, bench_cast_ray, bench_render_image);
+criterion_main!(benches);
@@ -1,36 +1,215 @@
-use std::{process::Termination, time::SystemTime};
+use std::{
+    fmt,
+    process::Termination,
+    time::{Duration, Instant},
+};
 
 use wgpu::SurfaceError;
 use winit::{
     dpi::PhysicalSize,
+    error::OsError,
     event::*,
     event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
-    window::{Window, WindowBuilder},
+    platform::run_return::EventLoopExtRunReturn,
+    window::{CursorGrabMode, Fullscreen, Window, WindowBuilder},
+};
+
+use crate::{
+    input::{Action, ActionMap, GamepadState},
+    profiling::{FrameProfiler, FrameTimings, GpuTimer},
+    renderer,
 };
 
+/// Default upper bound of a single frame delta (seconds).
+const DEFAULT_MAX_DELTA: f32 = 0.1;
+
 #[derive(Debug)]
 pub struct AppState {
-    previous_time: SystemTime,
+    /// Time of the last update, `None` before the first one so startup delays are not
+    /// reported as a frame delta
+    previous_time: Option<Instant>,
+    /// Time elapsed since the last update (seconds)
     elapsed_time: f32,
+    /// Time elapsed since the start, excluding paused time (seconds)
+    total_time: f32,
+    /// Upper bound of a single frame delta, avoids simulation jumps after long stalls (seconds)
+    max_delta: f32,
+    paused: bool,
+    /// Number of updates, paused ones included
+    frame_count: u64,
+    /// Stick state of the active gamepad, polled once per frame
+    gamepad: GamepadState,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
-            previous_time: SystemTime::now(),
+            previous_time: None,
             elapsed_time: 0.0,
+            total_time: 0.0,
+            max_delta: DEFAULT_MAX_DELTA,
+            paused: false,
+            frame_count: 0,
+            gamepad: GamepadState::default(),
         }
     }
 
     pub fn update(&mut self) {
-        let current_time = SystemTime::now();
-        let elapsed_time = current_time
-            .duration_since(self.previous_time)
-            .expect("Elapsed time calculation requires a monotonic clock")
-            .as_secs_f32()
-            / 1000.0;
-        self.previous_time = current_time;
-        self.elapsed_time = elapsed_time;
+        self.frame_count += 1;
+        let current_time = Instant::now();
+        let elapsed_time = self.previous_time.map_or(0.0, |previous_time| {
+            current_time
+                .saturating_duration_since(previous_time)
+                .as_secs_f32()
+        });
+        self.previous_time = Some(current_time);
+
+        if self.paused {
+            self.elapsed_time = 0.0;
+            return;
+        }
+
+        self.elapsed_time = elapsed_time.min(self.max_delta);
+        self.total_time += self.elapsed_time;
+    }
+
+    /// Stop the simulation clock, updates report a zero delta until resumed.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Restart the simulation clock, discarding the time spent paused.
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.previous_time = Some(Instant::now());
+        }
+    }
+
+    /// Set the upper bound of a single frame delta (seconds), negative values are clamped
+    /// to zero and NaN is ignored.
+    pub fn set_max_delta(&mut self, max_delta: f32) {
+        if !max_delta.is_nan() {
+            self.max_delta = max_delta.max(0.0);
+        }
+    }
+
+    pub fn max_delta(&self) -> f32 {
+        self.max_delta
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Time elapsed since the last update (seconds).
+    pub fn elapsed_time(&self) -> f32 {
+        self.elapsed_time
+    }
+
+    /// Time elapsed since the start, excluding paused time (seconds).
+    pub fn total_time(&self) -> f32 {
+        self.total_time
+    }
+
+    /// Number of updates since the start, `0` before the first update.
+    ///
+    /// Counts the paused frames too, unlike [`total_time`](Self::total_time).
+    pub fn frame(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Stick state of the active gamepad, at rest without the `gamepad` feature.
+    pub fn gamepad(&self) -> GamepadState {
+        self.gamepad
+    }
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Time after a resize request before the surface is reconfigured when no frame is drawn,
+/// frames apply the pending size before rendering.
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy)]
+struct PendingResize {
+    size: PhysicalSize<u32>,
+    requested_at: Instant,
+}
+
+/// Coalesces the window resize requests, only the last requested size is applied.
+#[derive(Debug, Default)]
+struct ResizeDebouncer {
+    pending: Option<PendingResize>,
+    /// The window has a zero size, the surface can not be acquired so nothing is drawn
+    minimized: bool,
+}
+
+impl ResizeDebouncer {
+    /// Store the new size, replacing any size still pending.
+    fn request(&mut self, size: PhysicalSize<u32>, now: Instant) {
+        self.minimized = size.width == 0 || size.height == 0;
+        self.pending = Some(PendingResize {
+            size,
+            requested_at: now,
+        });
+    }
+
+    /// Instant the pending resize is applied at if no frame is drawn before.
+    fn deadline(&self) -> Option<Instant> {
+        self.pending
+            .map(|pending| pending.requested_at + RESIZE_DEBOUNCE)
+    }
+
+    /// Take the last requested size, coalescing every resize since the previous one.
+    ///
+    /// Minimized, the surface keeps its size and the layer is resized once restored.
+    fn take(&mut self) -> Option<PhysicalSize<u32>> {
+        let pending = self.pending.take()?;
+        (!self.minimized).then_some(pending.size)
+    }
+
+    /// The last requested size is zero, update and render are skipped until it is restored.
+    fn minimized(&self) -> bool {
+        self.minimized
+    }
+}
+
+/// Paces the frames under an optional frame rate cap.
+#[derive(Debug, Default)]
+struct FrameLimiter {
+    /// Frame rate cap (frames per second), `None` runs as fast as the present mode allows
+    target_fps: Option<f32>,
+    /// Start of the last frame
+    last_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    fn set_target_fps(&mut self, target_fps: Option<f32>) {
+        self.target_fps = target_fps.filter(|fps| fps.is_finite() && *fps > 0.0);
+    }
+
+    fn target_fps(&self) -> Option<f32> {
+        self.target_fps
+    }
+
+    /// Sleep the remainder of the frame budget under the frame rate cap.
+    fn wait(&mut self) {
+        let Some(target_fps) = self.target_fps else {
+            return;
+        };
+
+        let budget = Duration::from_secs_f32(target_fps.recip());
+        if let Some(elapsed) = self.last_frame.map(|start| start.elapsed()) {
+            if elapsed < budget {
+                std::thread::sleep(budget - elapsed);
+            }
+        }
+        self.last_frame = Some(Instant::now());
     }
 }
 
@@ -38,6 +217,12 @@ pub struct Application<L: Layer + 'static> {
     layer: Option<L>,
     screen: Screen,
     state: AppState,
+    resize: ResizeDebouncer,
+    /// Instance that created the screen surface, used to recreate a lost surface
+    instance: Option<wgpu::Instance>,
+    #[cfg(feature = "gamepad")]
+    gamepad: Option<crate::input::GamepadInput>,
+    frame_limiter: FrameLimiter,
 }
 
 impl<L: Layer + 'static> Application<L> {
@@ -46,9 +231,51 @@ impl<L: Layer + 'static> Application<L> {
             screen,
             layer: None,
             state: AppState::new(),
+            resize: ResizeDebouncer::default(),
+            instance: None,
+            #[cfg(feature = "gamepad")]
+            gamepad: crate::input::GamepadInput::new()
+                .map_err(|err| tracing::warn!("gamepad input disabled: {}", err))
+                .ok(),
+            frame_limiter: FrameLimiter::default(),
         }
     }
 
+    /// Cap the frame rate, `None` or a non positive rate removes the cap.
+    pub fn set_target_fps(&mut self, target_fps: Option<f32>) {
+        self.frame_limiter.set_target_fps(target_fps);
+    }
+
+    pub fn target_fps(&self) -> Option<f32> {
+        self.frame_limiter.target_fps()
+    }
+
+    /// Shut the layer down and exit the event loop, with an error code if the shutdown failed.
+    fn close(&mut self, control_flow: &mut ControlFlow) {
+        control_flow.set_exit_with_code(0);
+        let app_res = self
+            .layer
+            .as_mut()
+            .unwrap()
+            .shutdown(&self.state, &mut self.screen);
+        if app_res.is_err() {
+            control_flow.set_exit_with_code(1);
+        }
+    }
+
+    /// Apply the pending resize to the screen and the layer.
+    fn apply_pending_resize(&mut self) {
+        let Some(size) = self.resize.take() else {
+            return;
+        };
+
+        self.screen.resize(size);
+        self.layer
+            .as_mut()
+            .unwrap()
+            .resize(size, &self.state, &mut self.screen);
+    }
+
     fn run(
         &mut self,
         event: Event<()>,
@@ -57,12 +284,29 @@ impl<L: Layer + 'static> Application<L> {
     ) {
         control_flow.set_wait();
 
-        if let Some(layer) = self.layer.as_mut() {
-            layer.process_event(&event, &mut self.screen);
+        dispatch_event(
+            self,
+            event,
+            |app, event| {
+                app.layer
+                    .as_mut()
+                    .is_some_and(|layer| layer.process_event(event, &mut app.screen))
+            },
+            |app, event| app.handle_event(event, control_flow),
+        );
+
+        // Wake up to apply the pending resize when no redraw is coming.
+        if let Some(deadline) = self.resize.deadline() {
+            if !matches!(control_flow, ControlFlow::ExitWithCode(_)) {
+                control_flow.set_wait_until(deadline);
+            }
         }
+    }
 
+    /// Default handling of the events not consumed by the layer.
+    fn handle_event(&mut self, event: Event<()>, control_flow: &mut ControlFlow) {
         match event {
-            Event::NewEvents(StartCause::Init) => {
+            Event::NewEvents(StartCause::Init) if self.layer.is_none() => {
                 self.layer = Some(L::start(&mut self.screen, &self.state));
             }
             Event::WindowEvent {
@@ -70,52 +314,83 @@ impl<L: Layer + 'static> Application<L> {
                 ref event,
             } => match event {
                 WindowEvent::CloseRequested if self.screen.window().id() == window_id => {
-                    control_flow.set_exit_with_code(0);
-                    let app_res = self
-                        .layer
-                        .as_mut()
-                        .unwrap()
-                        .shutdown(&self.state, &mut self.screen);
-                    if let Some(_) = app_res.err() {
-                        control_flow.set_exit_with_code(1);
-                    }
+                    self.close(control_flow);
                 }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(keycode),
+                            ..
+                        },
+                    ..
+                } => match self.screen.actions().action(*keycode) {
+                    Some(Action::Quit) => self.close(control_flow),
+                    Some(Action::ToggleFullscreen) => self.screen.toggle_fullscreen(),
+                    _ => {}
+                },
                 WindowEvent::Resized(physical_size) => {
-                    self.screen.resize(*physical_size);
-                    self.layer.as_mut().unwrap().resize(
-                        *physical_size,
-                        &self.state,
-                        &mut self.screen,
-                    );
+                    self.resize.request(*physical_size, Instant::now());
                 }
                 WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                    self.screen.resize(**new_inner_size);
-                    self.layer.as_mut().unwrap().resize(
-                        **new_inner_size,
-                        &self.state,
-                        &mut self.screen,
-                    );
+                    self.resize.request(**new_inner_size, Instant::now());
                 }
+                WindowEvent::Focused(true) => self.state.resume(),
+                WindowEvent::Focused(false) => self.state.pause(),
                 _ => {}
             },
             Event::MainEventsCleared => {
+                if self
+                    .resize
+                    .deadline()
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    self.apply_pending_resize();
+                }
+                self.frame_limiter.wait();
                 self.state.update();
-                self.screen.window().request_redraw();
+                #[cfg(feature = "gamepad")]
+                if let Some(gamepad) = self.gamepad.as_mut() {
+                    self.state.gamepad = gamepad.poll();
+                }
+                if !self.resize.minimized() {
+                    self.screen.window().request_redraw();
+                }
             }
-            Event::RedrawRequested(window_id) if self.screen.window().id() == window_id => {
-                self.layer
-                    .as_mut()
-                    .unwrap()
-                    .update(&self.state, &mut self.screen);
-
-                match self
-                    .layer
-                    .as_mut()
-                    .unwrap()
-                    .render(&self.state, &mut self.screen)
+            Event::RedrawRequested(window_id)
+                if self.screen.window().id() == window_id && !self.resize.minimized() =>
+            {
+                self.apply_pending_resize();
+                let update_start = Instant::now();
                 {
+                    let _span =
+                        tracing::debug_span!("update", elapsed_time = self.state.elapsed_time())
+                            .entered();
+                    self.layer
+                        .as_mut()
+                        .unwrap()
+                        .update(&self.state, &mut self.screen);
+                }
+                let render_start = Instant::now();
+
+                let render_result = {
+                    let _span = tracing::debug_span!(
+                        "render",
+                        width = self.screen.config.width,
+                        height = self.screen.config.height
+                    )
+                    .entered();
+                    self.render_frame()
+                };
+
+                let gpu_ms = self.screen.gpu_time_ms();
+                self.screen
+                    .frame_profiler
+                    .record(update_start, render_start, gpu_ms);
+
+                match render_result {
                     Ok(_) => {}
-                    Err(SurfaceError::Lost) => self.screen.resize_to_current(),
+                    Err(SurfaceError::Lost) => self.recover_lost_surface(),
                     Err(SurfaceError::OutOfMemory) => control_flow.set_exit_with_code(137),
                     Err(e) => tracing::error!("{:?}", e),
                 }
@@ -124,77 +399,615 @@ impl<L: Layer + 'static> Application<L> {
         }
     }
 
-    pub async fn init() {
-        let event_loop = EventLoop::new();
+    /// Acquire the surface texture, let the layer record its passes into the frame, submit
+    /// them in order and present the result.
+    fn render_frame(&mut self) -> Result<(), SurfaceError> {
+        let Some(surface) = &self.screen.surface else {
+            // A previous recreation failed, recovery is attempted again.
+            return Err(SurfaceError::Lost);
+        };
+        let output = surface.get_current_texture()?;
+        let mut frame = Frame::new(
+            output
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        );
+
+        self.layer
+            .as_mut()
+            .unwrap()
+            .render(&self.state, &mut self.screen, &mut frame)?;
+
+        self.screen.queue.submit(frame.command_buffers);
+        self.screen.gpu_timer.collect(&self.screen.device);
+        output.present();
+        Ok(())
+    }
+
+    /// Create the application with a window on the given event loop.
+    pub async fn with_event_loop(event_loop: &EventLoop<()>) -> Result<Self, ScreenError> {
+        Self::with_config(event_loop, &ScreenConfig::default()).await
+    }
+
+    /// Create the application with a window configured by `config` on the given event loop.
+    pub async fn with_config(
+        event_loop: &EventLoop<()>,
+        config: &ScreenConfig,
+    ) -> Result<Self, ScreenError> {
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
-        let screen = Screen::new(&event_loop, &instance).await;
+        let screen = Screen::new(event_loop, &instance, config).await?;
         let mut application = Self::new(screen);
+        application.instance = Some(instance);
+        application.set_target_fps(config.target_fps);
+        Ok(application)
+    }
+
+    /// Recreate the lost surface, only reconfigure it when the instance is unknown.
+    fn recover_lost_surface(&mut self) {
+        let Some(instance) = self.instance.as_ref() else {
+            self.screen.resize_to_current();
+            return;
+        };
+        if let Err(err) = self.screen.recreate_surface(instance) {
+            tracing::error!("failed to recreate the lost surface: {}", err);
+        }
+    }
+
+    /// Process the pending events of a single event loop iteration and return control
+    /// to the caller.
+    ///
+    /// Returns the exit code once the application requested to exit.
+    pub fn pump_events(&mut self, event_loop: &mut EventLoop<()>) -> Option<i32> {
+        let mut exit_code = None;
+        let mut iteration_done = false;
+        event_loop.run_return(|event, event_loop, control_flow| {
+            // The exit used to return control is sticky, ignore the remaining events.
+            if iteration_done {
+                return;
+            }
+
+            let iteration_end = matches!(event, Event::RedrawEventsCleared);
+            self.run(event, event_loop, control_flow);
+
+            if let ControlFlow::ExitWithCode(code) = *control_flow {
+                exit_code = Some(code);
+                iteration_done = true;
+            } else if iteration_end {
+                control_flow.set_exit();
+                iteration_done = true;
+            }
+        });
+        exit_code
+    }
+
+    pub fn layer(&self) -> Option<&L> {
+        self.layer.as_ref()
+    }
+
+    /// Replace the running layer, shutting the current layer down before starting the new
+    /// one with `start`, usually [`Layer::start`].
+    ///
+    /// Layers of different kinds can be swapped by implementing the layer on an enum of them.
+    /// The new layer replaces the current one even when its shutdown fails, returning the
+    /// shutdown error.
+    pub fn set_layer(
+        &mut self,
+        start: impl FnOnce(&mut Screen, &AppState) -> L,
+    ) -> Result<(), L::LayerErr> {
+        let state = &self.state;
+        replace_layer(
+            &mut self.layer,
+            &mut self.screen,
+            |layer, screen| layer.shutdown(state, screen),
+            |screen| start(screen, state),
+        )
+    }
+
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    pub async fn init() {
+        ApplicationBuilder::new().run::<L>().await;
+    }
+}
+
+/// Configuration of the application window and graphics device.
+#[derive(Debug, Clone)]
+pub struct ScreenConfig {
+    pub window: WindowBuilder,
+    pub power_preference: wgpu::PowerPreference,
+    /// Only accept a software adapter
+    pub force_fallback_adapter: bool,
+    /// Device features, `None` requests every feature supported by the adapter
+    pub features: Option<wgpu::Features>,
+    pub limits: wgpu::Limits,
+    /// Surface present mode, `None` selects the first mode supported by the surface
+    pub present_mode: Option<wgpu::PresentMode>,
+    /// Requested MSAA sample count, clamped to the counts supported by the surface format
+    pub sample_count: u32,
+    /// Frame rate cap (frames per second), `None` runs as fast as the present mode allows
+    pub target_fps: Option<f32>,
+    /// Key bindings of the input actions
+    pub actions: ActionMap,
+}
+
+impl Default for ScreenConfig {
+    fn default() -> Self {
+        Self {
+            window: WindowBuilder::new(),
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            features: None,
+            limits: wgpu::Limits::default(),
+            present_mode: None,
+            sample_count: 1,
+            target_fps: None,
+            actions: ActionMap::default(),
+        }
+    }
+}
+
+/// Configure and run an [`Application`].
+#[derive(Debug, Clone, Default)]
+pub struct ApplicationBuilder {
+    config: ScreenConfig,
+}
+
+impl ApplicationBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn window(mut self, window: WindowBuilder) -> Self {
+        self.config.window = window;
+        self
+    }
+
+    pub fn power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.config.power_preference = power_preference;
+        self
+    }
+
+    pub fn force_fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+        self.config.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+
+    pub fn features(mut self, features: wgpu::Features) -> Self {
+        self.config.features = Some(features);
+        self
+    }
+
+    pub fn limits(mut self, limits: wgpu::Limits) -> Self {
+        self.config.limits = limits;
+        self
+    }
+
+    pub fn present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.config.present_mode = Some(present_mode);
+        self
+    }
+
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.config.sample_count = sample_count;
+        self
+    }
+
+    pub fn target_fps(mut self, target_fps: Option<f32>) -> Self {
+        self.config.target_fps = target_fps;
+        self
+    }
+
+    pub fn actions(mut self, actions: ActionMap) -> Self {
+        self.config.actions = actions;
+        self
+    }
+
+    pub fn config(&self) -> &ScreenConfig {
+        &self.config
+    }
+
+    /// Create the application on the given event loop without running it.
+    pub async fn build<L: Layer + 'static>(
+        self,
+        event_loop: &EventLoop<()>,
+    ) -> Result<Application<L>, ScreenError> {
+        Application::with_config(event_loop, &self.config).await
+    }
+
+    /// Create the application and run it until the window is closed.
+    ///
+    /// Returns without running when the screen can not be created.
+    pub async fn run<L: Layer + 'static>(self) {
+        let event_loop = EventLoop::new();
+        let mut application = match self.build::<L>(&event_loop).await {
+            Ok(application) => application,
+            Err(err) => {
+                tracing::error!("{}", err);
+                return;
+            }
+        };
         event_loop.run(move |event, event_loop, control_flow| {
             application.run(event, event_loop, control_flow);
         });
     }
 }
 
+/// Surface configuration for the given size built from the surface capabilities, preferring
+/// an sRGB format. The requested present mode falls back to the first supported one.
+///
+/// `None` when the surface is incompatible with the adapter and reports no format, present
+/// mode or alpha mode.
+fn surface_config(
+    capabilities: &wgpu::SurfaceCapabilities,
+    size: PhysicalSize<u32>,
+    present_mode: Option<wgpu::PresentMode>,
+) -> Option<wgpu::SurfaceConfiguration> {
+    let format = select_surface_format(&capabilities.formats)?;
+    let present_mode = match present_mode {
+        Some(mode) if capabilities.present_modes.contains(&mode) => mode,
+        Some(mode) => {
+            tracing::warn!("present mode {:?} not supported by the surface", mode);
+            *capabilities.present_modes.first()?
+        }
+        None => *capabilities.present_modes.first()?,
+    };
+    Some(wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format,
+        width: size.width,
+        height: size.height,
+        present_mode,
+        alpha_mode: *capabilities.alpha_modes.first()?,
+        view_formats: vec![],
+    })
+}
+
+/// Configuration of a recreated surface from its capabilities, keeping the present mode of
+/// the `previous` configuration when still supported and its size while minimized.
+fn recreated_surface_config(
+    capabilities: &wgpu::SurfaceCapabilities,
+    size: PhysicalSize<u32>,
+    previous: &wgpu::SurfaceConfiguration,
+) -> Result<wgpu::SurfaceConfiguration, ScreenError> {
+    let size = if size.width > 0 && size.height > 0 {
+        size
+    } else {
+        PhysicalSize::new(previous.width, previous.height)
+    };
+    surface_config(capabilities, size, Some(previous.present_mode))
+        .ok_or(ScreenError::IncompatibleSurface)
+}
+
+/// Offer the event to the layer first, the `default` handling only receives the events
+/// the layer did not consume. Returns whether the layer consumed the event.
+fn dispatch_event<T, E>(
+    target: &mut T,
+    event: E,
+    consume: impl FnOnce(&mut T, &E) -> bool,
+    default: impl FnOnce(&mut T, E),
+) -> bool {
+    let consumed = consume(target, &event);
+    if !consumed {
+        default(target, event);
+    }
+    consumed
+}
+
+/// Shut the layer in `slot` down, then start its replacement even if the shutdown failed.
+/// Returns the shutdown result.
+fn replace_layer<L, C, E>(
+    slot: &mut Option<L>,
+    context: &mut C,
+    shutdown: impl FnOnce(&mut L, &mut C) -> Result<(), E>,
+    start: impl FnOnce(&mut C) -> L,
+) -> Result<(), E> {
+    let result = match slot.take() {
+        Some(mut layer) => shutdown(&mut layer, context),
+        None => Ok(()),
+    };
+    *slot = Some(start(context));
+    result
+}
+
+/// Failure to create the [`Screen`].
+#[derive(Debug)]
+pub enum ScreenError {
+    Window(OsError),
+    Surface(wgpu::CreateSurfaceError),
+    /// No adapter satisfies the requested options
+    NoAdapter {
+        power_preference: wgpu::PowerPreference,
+        force_fallback_adapter: bool,
+    },
+    RequestDevice(wgpu::RequestDeviceError),
+    /// The surface reports no format, present mode or alpha mode the adapter supports
+    IncompatibleSurface,
+}
+
+impl fmt::Display for ScreenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Window(err) => write!(f, "failed to create the window: {}", err),
+            Self::Surface(err) => write!(f, "failed to create the surface: {}", err),
+            Self::NoAdapter {
+                power_preference,
+                force_fallback_adapter,
+            } => write!(
+                f,
+                "no compatible GPU adapter found (power preference: {:?}, fallback adapter: {})",
+                power_preference, force_fallback_adapter
+            ),
+            Self::RequestDevice(err) => write!(f, "failed to request the device: {}", err),
+            Self::IncompatibleSurface => write!(f, "the surface is not supported by the adapter"),
+        }
+    }
+}
+
+impl std::error::Error for ScreenError {}
+
+/// Surface texture being rendered in the current frame, with the command buffers recorded
+/// by the layer.
+pub struct Frame {
+    view: wgpu::TextureView,
+    command_buffers: Vec<wgpu::CommandBuffer>,
+}
+
+impl Frame {
+    fn new(view: wgpu::TextureView) -> Self {
+        Self {
+            view,
+            command_buffers: Vec::new(),
+        }
+    }
+
+    /// View of the surface texture presented at the end of the frame.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Queue the command buffer for submission, buffers execute in the order they are
+    /// submitted.
+    pub fn submit(&mut self, command_buffer: wgpu::CommandBuffer) {
+        self.command_buffers.push(command_buffer);
+    }
+}
+
 pub struct Screen {
-    pub surface: wgpu::Surface,
+    /// Window surface, `None` when recreating a lost surface failed
+    surface: Option<wgpu::Surface>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
+    pub gpu_timer: GpuTimer,
+    frame_profiler: FrameProfiler,
+    capabilities: wgpu::SurfaceCapabilities,
+    adapter: wgpu::Adapter,
+    sample_count: u32,
+    actions: ActionMap,
     window: Window,
 }
 
 impl Screen {
-    pub async fn new(event_loop: &EventLoopWindowTarget<()>, instance: &wgpu::Instance) -> Self {
-        let window = WindowBuilder::new().build(&event_loop).unwrap();
+    pub async fn new(
+        event_loop: &EventLoopWindowTarget<()>,
+        instance: &wgpu::Instance,
+        screen_config: &ScreenConfig,
+    ) -> Result<Self, ScreenError> {
+        let window = screen_config
+            .window
+            .clone()
+            .build(event_loop)
+            .map_err(ScreenError::Window)?;
 
         // SAFETY:
         // The surface needs to live as long as the window that created it.
         // Screen owns the window so this should be safe.
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+        let surface = unsafe { instance.create_surface(&window) }.map_err(ScreenError::Surface)?;
+        let adapter_options = wgpu::RequestAdapterOptions {
+            power_preference: screen_config.power_preference,
+            compatible_surface: Some(&surface),
+            force_fallback_adapter: screen_config.force_fallback_adapter,
+        };
+        let adapter =
+            instance
+                .request_adapter(&adapter_options)
+                .await
+                .ok_or(ScreenError::NoAdapter {
+                    power_preference: adapter_options.power_preference,
+                    force_fallback_adapter: adapter_options.force_fallback_adapter,
+                })?;
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    features: adapter.features(),
-                    limits: wgpu::Limits::default(),
+                    features: screen_config.features.unwrap_or_else(|| adapter.features()),
+                    limits: screen_config.limits.clone(),
                     label: None,
                 },
                 None,
             )
             .await
-            .unwrap();
+            .map_err(ScreenError::RequestDevice)?;
         let size = window.inner_size();
-        let config = surface
-            .get_default_config(&adapter, size.width, size.height)
-            .unwrap();
+        let capabilities = surface.get_capabilities(&adapter);
+        let config = surface_config(&capabilities, size, screen_config.present_mode)
+            .ok_or(ScreenError::IncompatibleSurface)?;
         surface.configure(&device, &config);
+        let gpu_timer = GpuTimer::new(&device, &queue);
 
-        Self {
+        let mut screen = Self {
             window,
-            surface,
+            surface: Some(surface),
             device,
             queue,
             config,
-        }
+            gpu_timer,
+            frame_profiler: FrameProfiler::default(),
+            capabilities,
+            adapter,
+            sample_count: 1,
+            actions: screen_config.actions.clone(),
+        };
+        screen.sample_count =
+            screen.clamp_sample_count(screen.config.format, screen_config.sample_count);
+        Ok(screen)
     }
 
     pub fn window(&self) -> &Window {
         &self.window
     }
 
+    pub fn adapter(&self) -> &wgpu::Adapter {
+        &self.adapter
+    }
+
+    /// Key bindings of the input actions.
+    pub fn actions(&self) -> &ActionMap {
+        &self.actions
+    }
+
+    pub fn actions_mut(&mut self) -> &mut ActionMap {
+        &mut self.actions
+    }
+
+    /// Switch between windowed and borderless fullscreen on the current monitor.
+    pub fn toggle_fullscreen(&self) {
+        let fullscreen = match self.window.fullscreen() {
+            Some(_) => None,
+            None => Some(Fullscreen::Borderless(None)),
+        };
+        self.window.set_fullscreen(fullscreen);
+    }
+
+    /// Highest MSAA sample count supported by the adapter for the texture format.
+    pub fn max_supported_sample_count(&self, format: wgpu::TextureFormat) -> u32 {
+        self.clamp_sample_count(format, u32::MAX)
+    }
+
+    /// Highest MSAA sample count supported for the texture format that does not exceed
+    /// the requested count.
+    pub fn clamp_sample_count(&self, format: wgpu::TextureFormat, requested: u32) -> u32 {
+        let flags = self.adapter.get_texture_format_features(format).flags;
+        [16, 8, 4, 2]
+            .into_iter()
+            .filter(|count| *count <= requested)
+            .find(|count| flags.sample_count_supported(*count))
+            .unwrap_or(1)
+    }
+
+    /// MSAA sample counts supported by the adapter for the texture format, in ascending order
+    /// starting at a single sample.
+    pub fn supported_sample_counts(&self, format: wgpu::TextureFormat) -> Vec<u32> {
+        let flags = self.adapter.get_texture_format_features(format).flags;
+        [1, 2, 4, 8, 16]
+            .into_iter()
+            .filter(|count| *count == 1 || flags.sample_count_supported(*count))
+            .collect()
+    }
+
+    /// Create a shader module from WGSL source, returning the validation error message
+    /// instead of panicking when the shader is invalid.
+    pub fn create_shader_checked(&self, source: &str) -> Result<wgpu::ShaderModule, String> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+
+        match pollster::block_on(self.device.pop_error_scope()) {
+            Some(err) => Err(err.to_string()),
+            None => Ok(module),
+        }
+    }
+
+    /// MSAA sample count requested in the [`ScreenConfig`], clamped to the counts supported
+    /// by the surface format.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Formats, present modes and alpha modes supported by the surface on the current adapter.
+    pub fn surface_capabilities(&self) -> &wgpu::SurfaceCapabilities {
+        &self.capabilities
+    }
+
+    /// Width over height of the surface, `1.0` while the surface has a zero dimension.
+    pub fn aspect_ratio(&self) -> f32 {
+        if self.config.width == 0 || self.config.height == 0 {
+            return 1.0;
+        }
+        self.config.width as f32 / self.config.height as f32
+    }
+
+    /// Texture format of the surface.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// Enable the per frame CPU and GPU timing, logged at the debug level.
+    pub fn set_frame_timing(&mut self, enabled: bool) {
+        self.frame_profiler.set_enabled(enabled);
+        self.gpu_timer.set_enabled(enabled);
+    }
+
+    pub fn frame_timing_enabled(&self) -> bool {
+        self.frame_profiler.is_enabled()
+    }
+
+    /// Timings of the last frame, `None` while frame timing is disabled.
+    pub fn frame_timings(&self) -> Option<FrameTimings> {
+        self.frame_profiler.last()
+    }
+
+    /// Capture and hide the cursor, or release it back to the user.
+    ///
+    /// Prefers locking the cursor in place and falls back to confining it to the window
+    /// on platforms where locking is not supported.
+    pub fn set_cursor_grab(&self, grabbed: bool) {
+        let result = if grabbed {
+            self.window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| self.window.set_cursor_grab(CursorGrabMode::Confined))
+        } else {
+            self.window.set_cursor_grab(CursorGrabMode::None)
+        };
+
+        if let Err(err) = result {
+            tracing::warn!("failed to set cursor grab: {}", err);
+        }
+        self.window.set_cursor_visible(!grabbed);
+    }
+
+    /// Block until the submitted GPU work completes, see [`renderer::poll_wait`].
+    pub fn poll_wait(&self) {
+        renderer::poll_wait(&self.device);
+    }
+
+    /// Process the completed GPU work without blocking, see [`renderer::poll`].
+    pub fn poll(&self) -> bool {
+        renderer::poll(&self.device)
+    }
+
+    /// Last measured GPU render time in milliseconds.
+    pub fn gpu_time_ms(&self) -> Option<f32> {
+        self.gpu_timer.last_ms()
+    }
+
     /// Resize the screen to new window size.
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
         }
     }
 
@@ -202,15 +1015,381 @@ impl Screen {
     pub fn resize_to_current(&mut self) {
         self.resize(self.window.inner_size());
     }
+
+    /// Create a new surface for the window, configured for the current window size from the
+    /// capabilities of the new surface.
+    ///
+    /// Stronger recovery than reconfiguring when the surface itself was lost. On failure the
+    /// screen is left without a surface and no frame is acquired until a recreation succeeds.
+    pub fn recreate_surface(&mut self, instance: &wgpu::Instance) -> Result<(), ScreenError> {
+        // Some platforms allow a single surface per window, the old one is released first.
+        self.surface = None;
+        // SAFETY:
+        // Screen owns both the window and the surface, see `Screen::new`.
+        let surface =
+            unsafe { instance.create_surface(&self.window) }.map_err(ScreenError::Surface)?;
+        let capabilities = surface.get_capabilities(&self.adapter);
+        let config =
+            recreated_surface_config(&capabilities, self.window.inner_size(), &self.config)?;
+        surface.configure(&self.device, &config);
+
+        self.surface = Some(surface);
+        self.capabilities = capabilities;
+        self.config = config;
+        Ok(())
+    }
+}
+
+/// Choose the surface format, preferring an sRGB variant so the output is gamma corrected
+/// consistently across backends.
+///
+/// Falls back to the first format when no sRGB format is supported, `None` without any
+/// format.
+pub fn select_surface_format(formats: &[wgpu::TextureFormat]) -> Option<wgpu::TextureFormat> {
+    formats
+        .iter()
+        .copied()
+        .find(|format| format.is_srgb())
+        .or_else(|| formats.first().copied())
+}
+
+/// Color attachment load operation for a layer, `None` loads the existing framebuffer content.
+pub fn color_load_op(clear_color: Option<wgpu::Color>) -> wgpu::LoadOp<wgpu::Color> {
+    match clear_color {
+        Some(color) => wgpu::LoadOp::Clear(color),
+        None => wgpu::LoadOp::Load,
+    }
 }
 
 pub trait Layer: Sized {
     type LayerErr: Termination + 'static;
 
     fn start(screen: &mut Screen, app: &AppState) -> Self;
-    fn process_event(&mut self, event: &Event<()>, screen: &mut Screen);
+    /// Handle an event, returning `true` when the event was consumed by the layer,
+    /// preventing the application default handling.
+    fn process_event(&mut self, event: &Event<()>, screen: &mut Screen) -> bool;
     fn resize(&mut self, new_size: PhysicalSize<u32>, app: &AppState, screen: &mut Screen);
     fn update(&mut self, app: &AppState, screen: &mut Screen);
-    fn render(&mut self, app: &AppState, screen: &mut Screen) -> Result<(), SurfaceError>;
+    /// Record the frame rendering, off-screen passes included, into one or more command
+    /// buffers submitted to the frame.
+    ///
+    /// The application submits every buffer to the queue in submission order, and presents
+    /// the frame once the layer returns. Passes recorded in the same encoder execute in the
+    /// order they were begun.
+    fn render(
+        &mut self,
+        app: &AppState,
+        screen: &mut Screen,
+        frame: &mut Frame,
+    ) -> Result<(), SurfaceError>;
+
+    /// Color used to clear the framebuffer before rendering the layer.
+    ///
+    /// Base layers clear the framebuffer, overlay layers should return `None` to keep the
+    /// content rendered by the layers beneath.
+    fn clear_color(&self) -> Option<wgpu::Color> {
+        Some(wgpu::Color::BLACK)
+    }
+
     fn shutdown(&mut self, app: &AppState, screen: &mut Screen) -> Result<(), Self::LayerErr>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// State whose last update happened `elapsed` ago.
+    fn state_updated_ago(elapsed: Duration) -> AppState {
+        let mut state = AppState::new();
+        state.previous_time = Some(Instant::now() - elapsed);
+        state
+    }
+
+    #[test]
+    fn delta_is_measured_in_seconds() {
+        let mut state = state_updated_ago(Duration::from_millis(50));
+        state.update();
+        assert!((0.05..=DEFAULT_MAX_DELTA).contains(&state.elapsed_time()));
+        assert_eq!(state.total_time(), state.elapsed_time());
+    }
+
+    #[test]
+    fn delta_is_clamped_to_max_delta() {
+        let mut state = state_updated_ago(Duration::from_secs(2));
+        state.update();
+        assert_eq!(state.elapsed_time(), DEFAULT_MAX_DELTA);
+
+        let mut state = state_updated_ago(Duration::from_secs(5));
+        state.set_max_delta(0.25);
+        state.update();
+        assert_eq!(state.elapsed_time(), 0.25);
+        assert_eq!(state.total_time(), 0.25);
+    }
+
+    #[test]
+    fn invalid_max_delta_is_rejected() {
+        let mut state = AppState::new();
+        state.set_max_delta(-1.0);
+        assert_eq!(state.max_delta(), 0.0);
+        state.set_max_delta(f32::NAN);
+        assert_eq!(state.max_delta(), 0.0);
+    }
+
+    #[test]
+    fn first_update_reports_a_zero_delta() {
+        let mut state = AppState::new();
+        std::thread::sleep(Duration::from_millis(5));
+        state.update();
+        assert_eq!(state.elapsed_time(), 0.0);
+    }
+
+    #[test]
+    fn paused_time_is_excluded_from_the_total() {
+        let mut state = state_updated_ago(Duration::from_secs(1));
+        state.pause();
+        state.update();
+        assert!(state.is_paused());
+        assert_eq!(state.elapsed_time(), 0.0);
+        assert_eq!(state.total_time(), 0.0);
+
+        // The time spent paused is discarded on resume.
+        state.previous_time = Some(Instant::now() - Duration::from_secs(1));
+        state.resume();
+        state.update();
+        assert!(!state.is_paused());
+        assert!(state.total_time() < 0.05);
+    }
+
+    #[test]
+    fn resize_burst_applies_only_the_last_size() {
+        let start = Instant::now();
+        let mut resize = ResizeDebouncer::default();
+        for (i, width) in [640, 800, 1024].into_iter().enumerate() {
+            resize.request(
+                PhysicalSize::new(width, 480),
+                start + Duration::from_millis(10 * i as u64),
+            );
+        }
+
+        assert_eq!(
+            resize.deadline(),
+            Some(start + Duration::from_millis(20) + RESIZE_DEBOUNCE)
+        );
+        assert_eq!(resize.take(), Some(PhysicalSize::new(1024, 480)));
+        assert_eq!(resize.take(), None);
+        assert_eq!(resize.deadline(), None);
+    }
+
+    #[test]
+    fn minimized_window_skips_the_resize_until_restored() {
+        let start = Instant::now();
+        let mut resize = ResizeDebouncer::default();
+        resize.request(PhysicalSize::new(0, 0), start);
+        assert!(resize.minimized());
+        assert_eq!(resize.take(), None);
+
+        resize.request(PhysicalSize::new(800, 600), start);
+        assert!(!resize.minimized());
+        assert_eq!(resize.take(), Some(PhysicalSize::new(800, 600)));
+    }
+
+    #[allow(deprecated)]
+    fn key_press(keycode: VirtualKeyCode) -> Event<'static, ()> {
+        Event::WindowEvent {
+            // SAFETY: the dummy ids are only compared, never passed to the platform.
+            window_id: unsafe { winit::window::WindowId::dummy() },
+            event: WindowEvent::KeyboardInput {
+                device_id: unsafe { DeviceId::dummy() },
+                input: KeyboardInput {
+                    scancode: 0,
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(keycode),
+                    modifiers: ModifiersState::empty(),
+                },
+                is_synthetic: false,
+            },
+        }
+    }
+
+    #[test]
+    fn consumed_key_event_skips_the_default_handling() {
+        let mut handled = Vec::new();
+
+        let consumed = dispatch_event(
+            &mut handled,
+            key_press(VirtualKeyCode::Escape),
+            |_, _| true,
+            |handled, event| handled.push(event),
+        );
+        assert!(consumed);
+        assert!(handled.is_empty());
+
+        let consumed = dispatch_event(
+            &mut handled,
+            key_press(VirtualKeyCode::Escape),
+            |_, _| false,
+            |handled, event| handled.push(event),
+        );
+        assert!(!consumed);
+        assert_eq!(handled.len(), 1);
+    }
+
+    #[test]
+    fn swapping_layers_shuts_down_the_old_one_and_starts_the_new_one_once() {
+        let mut layer = Some("first");
+        let mut calls = Vec::new();
+
+        let result = replace_layer(
+            &mut layer,
+            &mut calls,
+            |layer, calls| {
+                calls.push(format!("shutdown {}", layer));
+                Err("shutdown failed")
+            },
+            |calls| {
+                calls.push("start second".to_string());
+                "second"
+            },
+        );
+        assert_eq!(result, Err("shutdown failed"));
+        assert_eq!(calls, ["shutdown first", "start second"]);
+        assert_eq!(layer, Some("second"));
+    }
+
+    fn capabilities(formats: Vec<wgpu::TextureFormat>) -> wgpu::SurfaceCapabilities {
+        wgpu::SurfaceCapabilities {
+            formats,
+            present_modes: vec![wgpu::PresentMode::Fifo],
+            alpha_modes: vec![wgpu::CompositeAlphaMode::Opaque],
+        }
+    }
+
+    #[test]
+    fn surface_config_uses_a_supported_format() {
+        let capabilities = capabilities(vec![
+            wgpu::TextureFormat::Bgra8Unorm,
+            wgpu::TextureFormat::Rgba16Float,
+        ]);
+        let config = surface_config(&capabilities, PhysicalSize::new(800, 600), None).unwrap();
+        assert!(capabilities.formats.contains(&config.format));
+        assert_eq!((config.width, config.height), (800, 600));
+        assert_eq!(config.present_mode, wgpu::PresentMode::Fifo);
+    }
+
+    #[test]
+    fn srgb_surface_format_is_preferred() {
+        let formats = [
+            wgpu::TextureFormat::Bgra8Unorm,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureFormat::Bgra8UnormSrgb,
+        ];
+        assert_eq!(
+            select_surface_format(&formats),
+            Some(wgpu::TextureFormat::Rgba8UnormSrgb)
+        );
+        assert_eq!(
+            select_surface_format(&[wgpu::TextureFormat::Rgba16Float]),
+            Some(wgpu::TextureFormat::Rgba16Float)
+        );
+    }
+
+    #[test]
+    fn recreated_surface_uses_the_current_size_and_new_capabilities() {
+        let previous = surface_config(
+            &capabilities(vec![wgpu::TextureFormat::Bgra8UnormSrgb]),
+            PhysicalSize::new(800, 600),
+            None,
+        )
+        .unwrap();
+        let capabilities = capabilities(vec![
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        ]);
+
+        let config =
+            recreated_surface_config(&capabilities, PhysicalSize::new(1024, 768), &previous)
+                .unwrap();
+        assert_eq!((config.width, config.height), (1024, 768));
+        assert_eq!(config.format, wgpu::TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(config.present_mode, previous.present_mode);
+
+        // Minimized, the previous size is kept.
+        let config =
+            recreated_surface_config(&capabilities, PhysicalSize::new(0, 0), &previous).unwrap();
+        assert_eq!((config.width, config.height), (800, 600));
+    }
+
+    #[test]
+    fn recreated_surface_without_formats_is_incompatible() {
+        let previous = surface_config(
+            &capabilities(vec![wgpu::TextureFormat::Bgra8UnormSrgb]),
+            PhysicalSize::new(800, 600),
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            recreated_surface_config(
+                &capabilities(Vec::new()),
+                PhysicalSize::new(800, 600),
+                &previous
+            ),
+            Err(ScreenError::IncompatibleSurface)
+        ));
+    }
+
+    #[test]
+    fn surface_config_requires_a_format() {
+        let capabilities = capabilities(Vec::new());
+        assert!(surface_config(&capabilities, PhysicalSize::new(800, 600), None).is_none());
+    }
+
+    #[test]
+    fn clear_color_selects_the_load_op() {
+        assert_eq!(
+            color_load_op(Some(wgpu::Color::RED)),
+            wgpu::LoadOp::Clear(wgpu::Color::RED)
+        );
+        assert_eq!(color_load_op(None), wgpu::LoadOp::Load);
+    }
+
+    #[test]
+    fn frame_limiter_spaces_the_frames_by_the_budget() {
+        let mut limiter = FrameLimiter::default();
+        limiter.set_target_fps(Some(30.0));
+        let mut state = AppState::new();
+
+        let updates: Vec<Instant> = (0..4)
+            .map(|_| {
+                limiter.wait();
+                state.update();
+                Instant::now()
+            })
+            .collect();
+        for frame in updates.windows(2) {
+            assert!(frame[1] - frame[0] >= Duration::from_millis(33));
+        }
+    }
+
+    #[test]
+    fn non_positive_target_fps_removes_the_cap() {
+        let mut limiter = FrameLimiter::default();
+        limiter.set_target_fps(Some(0.0));
+        assert_eq!(limiter.target_fps(), None);
+        limiter.set_target_fps(Some(60.0));
+        assert_eq!(limiter.target_fps(), Some(60.0));
+    }
+
+    #[test]
+    fn frame_counts_updates_from_zero() {
+        let mut state = AppState::new();
+        assert_eq!(state.frame(), 0);
+        for frame in 1..=3 {
+            state.update();
+            assert_eq!(state.frame(), frame);
+        }
+
+        state.pause();
+        state.update();
+        assert_eq!(state.frame(), 4);
+    }
+}
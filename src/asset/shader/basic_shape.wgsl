@@ -1,6 +1,4 @@
-struct CameraUniform {
-    view_proj: mat4x4<f32>,
-};
+//!include "camera.wgsl"
 
 @group(1) @binding(0)
 var<uniform> camera: CameraUniform;
@@ -0,0 +1,3 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
@@ -1,6 +1,18 @@
-use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+use winit::{
+    dpi::PhysicalSize,
+    event::{ElementState, KeyboardInput, WindowEvent},
+};
 
-use crate::util::math::degree_to_radian;
+use crate::{
+    input::{Action, ActionMap, GamepadState},
+    raytracer::{ray::Ray, scene::Scene},
+    util::math::degree_to_radian,
+};
+
+/// Extra distance kept around the scene bounds when framing a scene
+const FRAME_MARGIN: f32 = 1.1;
+/// Smallest near plane distance and gap between the near and far planes
+const MIN_CLIP_DISTANCE: f32 = 1e-3;
 
 #[derive(Debug)]
 pub struct CameraProjection {
@@ -24,6 +36,32 @@ impl CameraProjection {
     pub fn get_projection(&self) -> glam::Mat4 {
         glam::Mat4::perspective_rh(self.fov, self.aspect_ratio, self.near, self.far)
     }
+
+    /// Set the near plane distance, clamped to stay positive and in front of the far plane.
+    ///
+    /// Staying positive takes precedence when the far plane is too close (or not a number).
+    pub fn set_near(&mut self, near: f32) {
+        if near.is_finite() {
+            self.near = near
+                .min(self.far - MIN_CLIP_DISTANCE)
+                .max(MIN_CLIP_DISTANCE);
+        }
+    }
+
+    /// Set the far plane distance, clamped to stay behind the near plane.
+    pub fn set_far(&mut self, far: f32) {
+        if far.is_finite() {
+            self.far = far.max(self.near + MIN_CLIP_DISTANCE);
+        }
+    }
+
+    /// Match the aspect ratio to the size, sizes with a zero dimension (e.g. a minimized
+    /// window) keep the current aspect ratio.
+    pub fn set_aspect_from_size(&mut self, size: PhysicalSize<u32>) {
+        if size.width > 0 && size.height > 0 {
+            self.aspect_ratio = size.width as f32 / size.height as f32;
+        }
+    }
 }
 
 impl Default for CameraProjection {
@@ -67,23 +105,182 @@ impl Camera {
     pub fn view_projection(&self) -> glam::Mat4 {
         self.projection.get_projection() * self.view.get_view()
     }
+
+    /// Move the camera back along its current viewing direction until the whole scene fits
+    /// the field of view, an empty scene leaves the camera unchanged.
+    pub fn frame_scene(&mut self, scene: &Scene) {
+        let Some((center, radius)) = scene.bounding_sphere() else {
+            return;
+        };
+
+        let half_fov_y = self.projection.fov * 0.5;
+        let half_fov_x = (half_fov_y.tan() * self.projection.aspect_ratio).atan();
+        let distance = radius * FRAME_MARGIN / half_fov_y.min(half_fov_x).sin();
+
+        let forward = self.view.rotation.inverse() * glam::Vec3::NEG_Z;
+        let eye = center - forward * distance;
+        self.view.position = -(self.view.rotation * eye);
+        self.projection.far = self.projection.far.max(distance + radius * FRAME_MARGIN);
+    }
+
+    /// Follow the new window size, re-framing `framed` so the whole scene stays visible,
+    /// otherwise only the aspect ratio changes.
+    pub fn resize(&mut self, size: PhysicalSize<u32>, framed: Option<&Scene>) {
+        self.projection.set_aspect_from_size(size);
+        if let Some(scene) = framed {
+            self.frame_scene(scene);
+        }
+    }
+
+    /// Rust expression rebuilding the camera, preceded by a comment with the orientation as
+    /// yaw, pitch and roll angles.
+    pub fn pose_literal(&self) -> String {
+        let (yaw, pitch, roll) = self.view.rotation.inverse().to_euler(glam::EulerRot::YXZ);
+        let position = self.view.position;
+        let rotation = self.view.rotation;
+        let projection = &self.projection;
+        format!(
+            "// yaw {:?}, pitch {:?}, roll {:?} (degrees)\n\
+             Camera {{\n    \
+                 view: CameraView::new(\n        \
+                     glam::Vec3::new({:?}, {:?}, {:?}),\n        \
+                     glam::Quat::from_xyzw({:?}, {:?}, {:?}, {:?}),\n    \
+                 ),\n    \
+                 projection: CameraProjection::new({:?}, {:?}, {:?}, {:?}),\n\
+             }}",
+            yaw.to_degrees(),
+            pitch.to_degrees(),
+            roll.to_degrees(),
+            position.x,
+            position.y,
+            position.z,
+            rotation.x,
+            rotation.y,
+            rotation.z,
+            rotation.w,
+            projection.fov,
+            projection.near,
+            projection.far,
+            projection.aspect_ratio,
+        )
+    }
+
+    /// World space ray from the camera position through the center of a pixel.
+    ///
+    /// Pixel coordinates start at the bottom left corner of the image.
+    pub fn ray_through_pixel(&self, px: u32, py: u32, width: u32, height: u32) -> Ray {
+        self.ray_through_subpixel(px, py, glam::Vec2::splat(0.5), width, height)
+    }
+
+    /// World space ray from the camera position through a point inside a pixel, `offset`
+    /// is the position within the pixel in the `[0, 1)` range.
+    pub fn ray_through_subpixel(
+        &self,
+        px: u32,
+        py: u32,
+        offset: glam::Vec2,
+        width: u32,
+        height: u32,
+    ) -> Ray {
+        self.ray_generator(width, height).ray(px, py, offset)
+    }
+
+    /// Rays through the center of every pixel of the image, row-major from the bottom row.
+    pub fn generate_rays(&self, width: u32, height: u32) -> Vec<Ray> {
+        let generator = self.ray_generator(width, height);
+        (0..height)
+            .flat_map(|py| (0..width).map(move |px| (px, py)))
+            .map(|(px, py)| generator.ray(px, py, glam::Vec2::splat(0.5)))
+            .collect()
+    }
+
+    /// Ray source of an image, computing the inverse camera matrices once for all its rays.
+    pub fn ray_generator(&self, width: u32, height: u32) -> RayGenerator {
+        let inverse_view = self.view.get_view().inverse();
+        RayGenerator {
+            origin: inverse_view.transform_point3(glam::Vec3::ZERO),
+            inverse_projection: self.projection.get_projection().inverse(),
+            inverse_view,
+            width,
+            height,
+        }
+    }
 }
 
-pub struct CameraController {
+/// Camera rays through the pixels of an image with a fixed size.
+#[derive(Debug, Clone, Copy)]
+pub struct RayGenerator {
+    origin: glam::Vec3,
+    inverse_projection: glam::Mat4,
+    inverse_view: glam::Mat4,
+    width: u32,
+    height: u32,
+}
+
+impl RayGenerator {
+    /// World space ray through a point inside a pixel, `offset` is the position within the
+    /// pixel in the `[0, 1)` range.
+    ///
+    /// Pixel coordinates start at the bottom left corner of the image.
+    pub fn ray(&self, px: u32, py: u32, offset: glam::Vec2) -> Ray {
+        let ndc = glam::Vec2::new(
+            (px as f32 + offset.x) / self.width as f32,
+            (py as f32 + offset.y) / self.height as f32,
+        ) * 2.0
+            - 1.0;
+
+        let target = self.inverse_projection * glam::Vec4::new(ndc.x, ndc.y, 1.0, 1.0);
+        let direction = target.truncate() / target.w;
+
+        Ray::new(self.origin, self.inverse_view.transform_vector3(direction))
+    }
+}
+
+/// Input driven camera movement.
+///
+/// Controllers track the input state from the window events and apply it to the camera
+/// once per frame in `update`.
+pub trait CameraController {
+    /// Track the input state of the actions bound in `actions`, returns `true` if the event
+    /// was consumed by the controller.
+    fn process_event(&mut self, event: &WindowEvent, actions: &ActionMap) -> bool;
+
+    /// Accumulate the raw mouse motion, ignored by default.
+    fn process_mouse_motion(&mut self, _delta: (f64, f64)) {}
+
+    /// Track the gamepad sticks state, ignored by default.
+    fn process_gamepad(&mut self, _gamepad: GamepadState) {}
+
+    /// Apply the accumulated input to the camera, `elapsed_time` in seconds.
+    fn update(&mut self, camera: &mut Camera, elapsed_time: f32);
+
+    /// Return the controller to its rest state, does nothing by default.
+    fn reset(&mut self) {}
+}
+
+/// Dolly camera controller, moves the camera along the view Z axis with the forward and
+/// backward actions.
+pub struct DollyController {
+    /// Movement speed (units per second)
     pub speed: f32,
+    forward: bool,
+    backward: bool,
+    gamepad: GamepadState,
 }
 
-impl CameraController {
+impl DollyController {
     pub fn new(speed: f32) -> Self {
-        Self { speed }
+        Self {
+            speed,
+            forward: false,
+            backward: false,
+            gamepad: GamepadState::default(),
+        }
     }
+}
 
-    pub fn process_events(
-        &self,
-        camera: &mut Camera,
-        event: &WindowEvent,
-        elapsed_time: f32,
-    ) -> bool {
+impl CameraController for DollyController {
+    fn process_event(&mut self, event: &WindowEvent, actions: &ActionMap) -> bool {
         match event {
             WindowEvent::KeyboardInput {
                 input:
@@ -93,18 +290,485 @@ impl CameraController {
                         ..
                     },
                 ..
-            } if *state == ElementState::Pressed => match keycode {
-                VirtualKeyCode::W => {
-                    camera.view.position.z += self.speed * elapsed_time;
-                    true
+            } => {
+                let pressed = *state == ElementState::Pressed;
+                match actions.action(*keycode) {
+                    Some(Action::MoveForward) => self.forward = pressed,
+                    Some(Action::MoveBackward) => self.backward = pressed,
+                    _ => return false,
                 }
-                VirtualKeyCode::S => {
-                    camera.view.position.z -= self.speed * elapsed_time;
-                    true
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn process_gamepad(&mut self, gamepad: GamepadState) {
+        self.gamepad = gamepad;
+    }
+
+    fn update(&mut self, camera: &mut Camera, elapsed_time: f32) {
+        let axis = self.forward as i32 as f32 - self.backward as i32 as f32;
+        let axis = (axis + self.gamepad.left_stick.y).clamp(-1.0, 1.0);
+        camera.view.position.z += axis * self.speed * elapsed_time;
+    }
+}
+
+/// Maximum pitch angle, avoids flipping the camera over the vertical axis.
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+/// Roll rotation speed (radians per second)
+const ROLL_SPEED: f32 = 1.0;
+/// Rotation speed of a fully deflected gamepad look stick (radians per second)
+const GAMEPAD_LOOK_SPEED: f32 = 2.0;
+
+/// Free flying camera controller.
+///
+/// Translates the camera in its local space with the movement actions, WASD (horizontal)
+/// and QE (vertical) by default, and rotates it with the mouse motion using yaw and pitch
+/// angles. Holding Ctrl makes the vertical actions roll the camera around its viewing
+/// direction instead. The gamepad left stick moves and the right stick looks around.
+pub struct FreeFlyController {
+    /// Movement speed (units per second)
+    pub speed: f32,
+    /// Rotation per mouse motion unit (radians)
+    pub sensitivity: f32,
+    yaw: f32,
+    pitch: f32,
+    roll: f32,
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+    roll_left: bool,
+    roll_right: bool,
+    /// Modifier switching QE from vertical movement to roll
+    roll_modifier: bool,
+    mouse_delta: glam::Vec2,
+    gamepad: GamepadState,
+}
+
+impl FreeFlyController {
+    pub fn new(speed: f32, sensitivity: f32) -> Self {
+        Self {
+            speed,
+            sensitivity,
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            forward: false,
+            backward: false,
+            left: false,
+            right: false,
+            up: false,
+            down: false,
+            roll_left: false,
+            roll_right: false,
+            roll_modifier: false,
+            mouse_delta: glam::Vec2::ZERO,
+            gamepad: GamepadState::default(),
+        }
+    }
+
+    /// Camera orientation in world space.
+    pub fn orientation(&self) -> glam::Quat {
+        glam::Quat::from_rotation_y(self.yaw)
+            * glam::Quat::from_rotation_x(self.pitch)
+            * glam::Quat::from_rotation_z(self.roll)
+    }
+
+    /// Level the camera, keeping the yaw and pitch.
+    pub fn reset_roll(&mut self) {
+        self.roll = 0.0;
+    }
+}
+
+impl CameraController for FreeFlyController {
+    /// Track the movement actions state, returns `true` if the event was a key bound to a
+    /// movement action.
+    fn process_event(&mut self, event: &WindowEvent, actions: &ActionMap) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => {
+                let pressed = *state == ElementState::Pressed;
+                match actions.action(*keycode) {
+                    Some(Action::MoveForward) => self.forward = pressed,
+                    Some(Action::MoveBackward) => self.backward = pressed,
+                    Some(Action::MoveLeft) => self.left = pressed,
+                    Some(Action::MoveRight) => self.right = pressed,
+                    Some(Action::MoveUp) => {
+                        self.up = pressed && !self.roll_modifier;
+                        self.roll_right = pressed && self.roll_modifier;
+                    }
+                    Some(Action::MoveDown) => {
+                        self.down = pressed && !self.roll_modifier;
+                        self.roll_left = pressed && self.roll_modifier;
+                    }
+                    _ => return false,
                 }
-                _ => false,
-            },
+                true
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.roll_modifier = modifiers.ctrl();
+                false
+            }
             _ => false,
         }
     }
+
+    /// Accumulate the raw mouse motion to be applied in the next update.
+    fn process_mouse_motion(&mut self, delta: (f64, f64)) {
+        self.mouse_delta += glam::Vec2::new(delta.0 as f32, delta.1 as f32);
+    }
+
+    /// Apply the accumulated input to the camera, `elapsed_time` in seconds.
+    fn update(&mut self, camera: &mut Camera, elapsed_time: f32) {
+        self.yaw -= self.mouse_delta.x * self.sensitivity;
+        self.pitch =
+            (self.pitch - self.mouse_delta.y * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        self.mouse_delta = glam::Vec2::ZERO;
+
+        let look = self.gamepad.right_stick * GAMEPAD_LOOK_SPEED * elapsed_time;
+        self.yaw -= look.x;
+        self.pitch = (self.pitch + look.y).clamp(-MAX_PITCH, MAX_PITCH);
+
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+        // Positive angles roll counterclockwise, to the left, as seen through the camera.
+        self.roll += axis(self.roll_left, self.roll_right) * ROLL_SPEED * elapsed_time;
+
+        let orientation = self.orientation();
+        let forward = orientation * glam::Vec3::NEG_Z;
+        let right = orientation * glam::Vec3::X;
+
+        let stick = self.gamepad.left_stick;
+        let direction = forward * (axis(self.forward, self.backward) + stick.y)
+            + right * (axis(self.right, self.left) + stick.x)
+            + glam::Vec3::Y * axis(self.up, self.down);
+
+        // The camera view stores the world to camera transform, the camera placement in the
+        // world is its inverse.
+        let eye = -(camera.view.rotation.inverse() * camera.view.position);
+        // Clamping instead of normalizing keeps the partial stick deflections proportional.
+        let eye = eye + direction.clamp_length_max(1.0) * self.speed * elapsed_time;
+        camera.view.rotation = orientation.inverse();
+        camera.view.position = -(camera.view.rotation * eye);
+    }
+
+    /// Track the gamepad sticks state to be applied in the next update.
+    fn process_gamepad(&mut self, gamepad: GamepadState) {
+        self.gamepad = gamepad;
+    }
+
+    /// Level the camera.
+    fn reset(&mut self) {
+        self.reset_roll();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::VirtualKeyCode;
+
+    use super::*;
+
+    #[allow(deprecated)]
+    fn key(keycode: VirtualKeyCode, state: ElementState) -> WindowEvent<'static> {
+        WindowEvent::KeyboardInput {
+            // SAFETY: the dummy device is only compared, never dereferenced.
+            device_id: unsafe { winit::event::DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(keycode),
+                modifiers: winit::event::ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        }
+    }
+
+    /// Camera placement in the world.
+    fn eye(camera: &Camera) -> glam::Vec3 {
+        -(camera.view.rotation.inverse() * camera.view.position)
+    }
+
+    #[test]
+    fn forward_follows_the_yaw() {
+        let actions = ActionMap::default();
+        let mut camera = Camera::default();
+        let right = camera.view.rotation.inverse() * glam::Vec3::X;
+        let mut controller = FreeFlyController::new(2.0, 1.0);
+
+        // Turn 90 degrees to the right.
+        controller.process_mouse_motion((std::f64::consts::FRAC_PI_2, 0.0));
+        controller.update(&mut camera, 0.0);
+        let start = eye(&camera);
+
+        controller.process_event(&key(VirtualKeyCode::W, ElementState::Pressed), &actions);
+        controller.update(&mut camera, 0.5);
+        assert!((eye(&camera) - start - right).length() < 1e-5);
+    }
+
+    fn square_camera() -> Camera {
+        let mut camera = Camera::default();
+        camera.projection.aspect_ratio = 1.0;
+        camera
+    }
+
+    #[test]
+    fn center_pixel_ray_points_forward() {
+        let camera = square_camera();
+        let ray = camera.ray_through_pixel(1, 1, 3, 3);
+        assert!(ray
+            .origin
+            .abs_diff_eq(glam::Vec3::new(0.0, 0.0, 10.0), 1e-5));
+        assert!(ray
+            .direction
+            .normalize()
+            .abs_diff_eq(glam::Vec3::NEG_Z, 1e-5));
+    }
+
+    #[test]
+    fn generated_rays_cover_the_image_row_major() {
+        let camera = square_camera();
+        let rays = camera.generate_rays(3, 3);
+        assert_eq!(rays.len(), 9);
+
+        let center = camera.ray_through_pixel(1, 1, 3, 3);
+        assert_eq!(rays[4].origin, center.origin);
+        assert!(rays[4].direction.abs_diff_eq(center.direction, 1e-6));
+        let bottom_right = camera.ray_through_pixel(2, 0, 3, 3);
+        assert!(rays[2].direction.abs_diff_eq(bottom_right.direction, 1e-6));
+    }
+
+    #[test]
+    fn pixel_rows_start_at_the_bottom() {
+        let camera = square_camera();
+        let right = camera.ray_through_pixel(2, 1, 3, 3).direction;
+        let top = camera.ray_through_pixel(1, 2, 3, 3).direction;
+        assert!(right.x > 0.0 && right.y.abs() < 1e-5);
+        assert!(top.y > 0.0 && top.x.abs() < 1e-5);
+    }
+
+    #[test]
+    fn aspect_ratio_follows_the_size() {
+        let mut projection = CameraProjection::default();
+        projection.set_aspect_from_size(PhysicalSize::new(1920, 1080));
+        assert!((projection.aspect_ratio - 16.0 / 9.0).abs() < 1e-5);
+
+        projection.set_aspect_from_size(PhysicalSize::new(1920, 0));
+        assert!((projection.aspect_ratio - 16.0 / 9.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn framed_scene_projects_inside_the_view() {
+        let mut scene = Scene::default();
+        let centers = [
+            glam::Vec3::new(-10.0, 0.0, -3.0),
+            glam::Vec3::new(10.0, 4.0, -20.0),
+        ];
+        for position in centers {
+            scene.add_sphere(crate::raytracer::scene::Sphere {
+                position,
+                radius: 1.0,
+                ..Default::default()
+            });
+        }
+
+        let mut camera = Camera::default();
+        camera.frame_scene(&scene);
+        for center in centers {
+            let clip = camera.view_projection() * center.extend(1.0);
+            assert!(clip.w > 0.0);
+            let ndc = clip.truncate() / clip.w;
+            assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0, "{:?}", ndc);
+            assert!((0.0..=1.0).contains(&ndc.z), "{:?}", ndc);
+        }
+    }
+
+    #[test]
+    fn resize_keeps_the_scene_framed() {
+        let mut scene = Scene::default();
+        let centers = [
+            glam::Vec3::new(-8.0, 0.0, 0.0),
+            glam::Vec3::new(8.0, 0.0, 0.0),
+        ];
+        for position in centers {
+            scene.add_sphere(crate::raytracer::scene::Sphere {
+                position,
+                radius: 1.0,
+                ..Default::default()
+            });
+        }
+        let ndc_x = |camera: &Camera, center: glam::Vec3| {
+            let clip = camera.view_projection() * center.extend(1.0);
+            clip.x / clip.w
+        };
+
+        let mut stretched = Camera::default();
+        stretched.frame_scene(&scene);
+        stretched.resize(PhysicalSize::new(400, 1000), None);
+        assert!(centers
+            .iter()
+            .any(|&center| ndc_x(&stretched, center).abs() > 1.0));
+
+        let mut camera = Camera::default();
+        camera.frame_scene(&scene);
+        camera.resize(PhysicalSize::new(400, 1000), Some(&scene));
+        for center in centers {
+            assert!(ndc_x(&camera, center).abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn framing_an_empty_scene_keeps_the_camera() {
+        let mut camera = Camera::default();
+        camera.frame_scene(&Scene::default());
+        assert_eq!(
+            camera.view_projection(),
+            Camera::default().view_projection()
+        );
+    }
+
+    #[test]
+    fn ctrl_turns_the_vertical_actions_into_roll() {
+        let actions = ActionMap::default();
+        let mut camera = Camera::default();
+        let mut controller = FreeFlyController::new(1.0, 0.0);
+        controller.process_event(
+            &WindowEvent::ModifiersChanged(winit::event::ModifiersState::CTRL),
+            &actions,
+        );
+        assert!(controller.process_event(&key(VirtualKeyCode::Q, ElementState::Pressed), &actions));
+        controller.update(&mut camera, 0.5);
+
+        // Rolling to the left tilts the camera up vector toward -X, without moving it.
+        let up = controller.orientation() * glam::Vec3::Y;
+        assert!((up.x + (ROLL_SPEED * 0.5).sin()).abs() < 1e-5);
+        assert!(camera
+            .ray_through_pixel(0, 0, 1, 1)
+            .origin
+            .abs_diff_eq(glam::Vec3::new(0.0, 0.0, 10.0), 1e-5));
+
+        controller.reset_roll();
+        assert!(controller
+            .orientation()
+            .abs_diff_eq(glam::Quat::IDENTITY, 1e-6));
+    }
+
+    #[test]
+    fn vertical_actions_move_without_ctrl() {
+        let actions = ActionMap::default();
+        let mut camera = Camera::default();
+        let mut controller = FreeFlyController::new(2.0, 0.0);
+        controller.process_event(&key(VirtualKeyCode::E, ElementState::Pressed), &actions);
+        controller.update(&mut camera, 0.5);
+
+        assert_eq!(controller.orientation(), glam::Quat::IDENTITY);
+        assert!(eye(&camera).abs_diff_eq(glam::Vec3::new(0.0, 1.0, 10.0), 1e-5));
+    }
+
+    #[test]
+    fn near_plane_stays_between_zero_and_far() {
+        let mut projection = CameraProjection::new(1.0, 0.1, 100.0, 1.0);
+        projection.set_near(50.0);
+        assert_eq!(projection.near, 50.0);
+        projection.set_near(200.0);
+        assert_eq!(projection.near, 100.0 - MIN_CLIP_DISTANCE);
+        projection.set_near(-1.0);
+        assert_eq!(projection.near, MIN_CLIP_DISTANCE);
+        projection.set_near(f32::NAN);
+        assert_eq!(projection.near, MIN_CLIP_DISTANCE);
+    }
+
+    #[test]
+    fn near_plane_stays_positive_with_a_close_far_plane() {
+        let mut projection = CameraProjection::new(1.0, 0.1, 1.5e-3, 1.0);
+        projection.set_near(0.5);
+        assert_eq!(projection.near, MIN_CLIP_DISTANCE);
+    }
+
+    #[test]
+    fn far_plane_stays_behind_near() {
+        let mut projection = CameraProjection::new(1.0, 0.1, 100.0, 1.0);
+        projection.set_far(0.0);
+        assert_eq!(projection.far, 0.1 + MIN_CLIP_DISTANCE);
+        projection.set_far(f32::INFINITY);
+        assert_eq!(projection.far, 0.1 + MIN_CLIP_DISTANCE);
+        projection.set_far(20.0);
+        assert_eq!(projection.far, 20.0);
+    }
+
+    #[test]
+    fn controllers_are_swapped_on_a_shared_camera() {
+        let actions = ActionMap::default();
+        let mut camera = Camera::default();
+        let mut controllers: Vec<Box<dyn CameraController>> = vec![
+            Box::new(FreeFlyController::new(2.0, 0.0)),
+            Box::new(DollyController::new(2.0)),
+        ];
+        let start = eye(&camera);
+
+        // The free fly controller moves forward, toward -Z.
+        assert!(
+            controllers[0].process_event(&key(VirtualKeyCode::W, ElementState::Pressed), &actions)
+        );
+        controllers[0].update(&mut camera, 0.5);
+        assert!(eye(&camera).abs_diff_eq(start + glam::Vec3::NEG_Z, 1e-5));
+
+        // The dolly controller picks up the camera where the free fly one left it.
+        controllers.rotate_left(1);
+        assert!(
+            controllers[0].process_event(&key(VirtualKeyCode::S, ElementState::Pressed), &actions)
+        );
+        controllers[0].update(&mut camera, 0.5);
+        // Backing off by the same distance brings the camera to its start.
+        assert!(eye(&camera).abs_diff_eq(start, 1e-5));
+        assert!(
+            !controllers[0].process_event(&key(VirtualKeyCode::A, ElementState::Pressed), &actions)
+        );
+    }
+
+    #[test]
+    fn left_stick_moves_proportionally_to_its_deflection() {
+        let mut camera = Camera::default();
+        let right = camera.view.rotation.inverse() * glam::Vec3::X;
+        let forward = camera.view.rotation.inverse() * glam::Vec3::NEG_Z;
+        let mut controller = FreeFlyController::new(2.0, 1.0);
+        let start = eye(&camera);
+
+        controller.process_gamepad(GamepadState::new(
+            glam::Vec2::new(0.5, 0.0),
+            glam::Vec2::ZERO,
+        ));
+        controller.update(&mut camera, 1.0);
+        assert!((eye(&camera) - start - right).length() < 1e-5);
+
+        let start = eye(&camera);
+        controller.process_gamepad(GamepadState::new(
+            glam::Vec2::new(0.0, 0.25),
+            glam::Vec2::ZERO,
+        ));
+        controller.update(&mut camera, 1.0);
+        assert!((eye(&camera) - start - forward * 0.5).length() < 1e-5);
+    }
+
+    #[test]
+    fn pose_literal_contains_the_position_and_fov() {
+        let camera = Camera {
+            view: CameraView::new(glam::Vec3::new(1.5, -2.0, 3.25), glam::Quat::IDENTITY),
+            projection: CameraProjection::new(0.75, 0.1, 100.0, 1.0),
+        };
+
+        let literal = camera.pose_literal();
+        assert!(literal.contains("glam::Vec3::new(1.5, -2.0, 3.25)"));
+        assert!(literal.contains("CameraProjection::new(0.75, 0.1, 100.0, 1.0)"));
+    }
 }
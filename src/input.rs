@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use winit::event::VirtualKeyCode;
+
+/// Logical input action, bound to keys by an [`ActionMap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Quit,
+    ToggleFullscreen,
+}
+
+/// Key bindings of the logical actions.
+///
+/// A key triggers at most one action, an action can be bound to several keys.
+#[derive(Debug, Clone)]
+pub struct ActionMap {
+    bindings: HashMap<VirtualKeyCode, Action>,
+}
+
+impl ActionMap {
+    /// Map without any binding.
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Bind the key to the action, replacing the previous action of the key.
+    pub fn bind(&mut self, key: VirtualKeyCode, action: Action) {
+        self.bindings.insert(key, action);
+    }
+
+    /// Bind the action to the key only, removing its other bindings.
+    pub fn rebind(&mut self, action: Action, key: VirtualKeyCode) {
+        self.bindings.retain(|_, bound| *bound != action);
+        self.bind(key, action);
+    }
+
+    pub fn unbind(&mut self, key: VirtualKeyCode) -> Option<Action> {
+        self.bindings.remove(&key)
+    }
+
+    /// Action triggered by the key, `None` for unbound keys.
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.bindings.get(&key).copied()
+    }
+
+    /// Keys bound to the action, in no particular order.
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = VirtualKeyCode> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, bound)| **bound == action)
+            .map(|(key, _)| *key)
+    }
+}
+
+impl Default for ActionMap {
+    /// WASD movement with QE for vertical movement, Escape quits and F11 toggles fullscreen.
+    fn default() -> Self {
+        let mut map = Self::empty();
+        map.bind(VirtualKeyCode::W, Action::MoveForward);
+        map.bind(VirtualKeyCode::S, Action::MoveBackward);
+        map.bind(VirtualKeyCode::A, Action::MoveLeft);
+        map.bind(VirtualKeyCode::D, Action::MoveRight);
+        map.bind(VirtualKeyCode::E, Action::MoveUp);
+        map.bind(VirtualKeyCode::Q, Action::MoveDown);
+        map.bind(VirtualKeyCode::Escape, Action::Quit);
+        map.bind(VirtualKeyCode::F11, Action::ToggleFullscreen);
+        map
+    }
+}
+
+/// Stick deflection ignored around the rest position
+const STICK_DEADZONE: f32 = 0.1;
+
+/// Analog stick state of the active gamepad, both sticks rest at zero without a gamepad.
+///
+/// Each axis is in the `[-1, 1]` range, positive X points right and positive Y up.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GamepadState {
+    /// Movement stick
+    pub left_stick: glam::Vec2,
+    /// Look stick
+    pub right_stick: glam::Vec2,
+}
+
+impl GamepadState {
+    /// Build the state from the raw stick values, zeroing the deflections inside the deadzone.
+    pub fn new(left_stick: glam::Vec2, right_stick: glam::Vec2) -> Self {
+        let deadzone = |stick: glam::Vec2| {
+            if stick.length() < STICK_DEADZONE {
+                glam::Vec2::ZERO
+            } else {
+                stick.clamp(glam::Vec2::NEG_ONE, glam::Vec2::ONE)
+            }
+        };
+        Self {
+            left_stick: deadzone(left_stick),
+            right_stick: deadzone(right_stick),
+        }
+    }
+}
+
+/// Gamepad event source, follows the last used gamepad and handles hot-plugging.
+#[cfg(feature = "gamepad")]
+pub struct GamepadInput {
+    gilrs: gilrs::Gilrs,
+    active: Option<gilrs::GamepadId>,
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadInput {
+    pub fn new() -> Result<Self, Box<gilrs::Error>> {
+        let gilrs = gilrs::Gilrs::new().map_err(Box::new)?;
+        let active = gilrs.gamepads().next().map(|(id, _)| id);
+        Ok(Self { gilrs, active })
+    }
+
+    /// Process the pending gamepad events and read the sticks of the active gamepad.
+    pub fn poll(&mut self) -> GamepadState {
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            match event {
+                gilrs::EventType::Connected => {
+                    tracing::info!("gamepad connected: {}", self.gilrs.gamepad(id).name());
+                    self.active.get_or_insert(id);
+                }
+                gilrs::EventType::Disconnected => {
+                    tracing::info!("gamepad disconnected: {}", self.gilrs.gamepad(id).name());
+                    if self.active == Some(id) {
+                        self.active = self.gilrs.gamepads().next().map(|(id, _)| id);
+                    }
+                }
+                _ => self.active = Some(id),
+            }
+        }
+
+        let Some(gamepad) = self.active.map(|id| self.gilrs.gamepad(id)) else {
+            return GamepadState::default();
+        };
+        let stick = |x, y| glam::Vec2::new(gamepad.value(x), gamepad.value(y));
+        GamepadState::new(
+            stick(gilrs::Axis::LeftStickX, gilrs::Axis::LeftStickY),
+            stick(gilrs::Axis::RightStickX, gilrs::Axis::RightStickY),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebind_replaces_every_key_of_the_action() {
+        let mut map = ActionMap::default();
+        map.bind(VirtualKeyCode::Up, Action::MoveForward);
+        map.rebind(Action::MoveForward, VirtualKeyCode::Z);
+
+        assert_eq!(
+            map.keys(Action::MoveForward).collect::<Vec<_>>(),
+            [VirtualKeyCode::Z]
+        );
+        assert_eq!(map.action(VirtualKeyCode::W), None);
+        assert_eq!(map.action(VirtualKeyCode::Up), None);
+        assert_eq!(map.action(VirtualKeyCode::S), Some(Action::MoveBackward));
+    }
+
+    #[test]
+    fn rebind_takes_the_key_from_its_previous_action() {
+        let mut map = ActionMap::default();
+        map.rebind(Action::MoveForward, VirtualKeyCode::S);
+
+        assert_eq!(map.action(VirtualKeyCode::S), Some(Action::MoveForward));
+        assert_eq!(map.keys(Action::MoveBackward).count(), 0);
+    }
+
+    #[test]
+    fn unbind_returns_the_removed_action() {
+        let mut map = ActionMap::default();
+        assert_eq!(map.unbind(VirtualKeyCode::Escape), Some(Action::Quit));
+        assert_eq!(map.unbind(VirtualKeyCode::Escape), None);
+        assert_eq!(map.action(VirtualKeyCode::Escape), None);
+    }
+
+    #[test]
+    fn stick_inside_the_deadzone_rests() {
+        let state = GamepadState::new(glam::Vec2::new(0.05, 0.05), glam::Vec2::new(2.0, 0.5));
+
+        assert_eq!(state.left_stick, glam::Vec2::ZERO);
+        assert_eq!(state.right_stick, glam::Vec2::new(1.0, 0.5));
+    }
+}
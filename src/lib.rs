@@ -1,5 +1,10 @@
 pub mod application;
 pub mod camera;
+pub mod input;
+pub mod profiling;
+pub mod raytracer;
 pub mod renderer;
+pub mod shader;
+pub mod text;
 pub mod texture;
 pub mod util;
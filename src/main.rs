@@ -1,46 +1,85 @@
 use glam::*;
-use image::{Rgba, RgbaImage};
-use ray::Ray;
+use image::{imageops::FilterType, Rgba, RgbaImage};
+#[cfg(feature = "hot-reload")]
+use rust_wgpu_lib::shader::ShaderWatcher;
 use rust_wgpu_lib::{
-    application::{AppState, Application, Layer, Screen},
-    camera::{Camera, CameraController},
-    renderer::{IndexBuffer, Vertex, VertexBuffer, QUAD_INDICES, QUAD_VERTICES},
+    application::{color_load_op, AppState, ApplicationBuilder, Frame, Layer, Screen},
+    camera::{Camera, CameraController, DollyController, FreeFlyController},
+    raytracer::{
+        light::PointLight,
+        render_image, render_to_texture,
+        scene::{Aabb, Scene, Sphere, SphereHandle},
+        InteractiveResolution, RenderSettings, TracerPool, MAX_BOUNCE_DEPTH,
+    },
+    renderer::{
+        self, CullMode, DebugLines, DrawCommand, IndexBuffer, Mesh, Vertex, VertexBuffer,
+        QUAD_CULL_MODE, QUAD_INDICES, QUAD_VERTICES,
+    },
+    shader,
+    text::TextOverlay,
     texture::Texture,
 };
-use scene::{Scene, Sphere};
 use wgpu::{
-    include_wgsl, util::DeviceExt, CommandEncoderDescriptor, PipelineLayoutDescriptor,
-    RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor,
-    TextureViewDescriptor,
+    util::DeviceExt, CommandEncoderDescriptor, PipelineLayoutDescriptor, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipelineDescriptor, TextureViewDescriptor,
+};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{
+        DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent,
+    },
+    window::WindowBuilder,
 };
-use winit::{dpi::PhysicalSize, event::Event};
-
-mod ray;
-mod scene;
 
 struct RayTracingCPU {
     camera: Camera,
-    camera_controller: CameraController,
-    camera_buffer: wgpu::Buffer,
+    /// Rotate the camera with the mouse while the cursor is grabbed
+    mouse_look: bool,
+    /// Last known cursor position in the window
+    cursor_position: PhysicalPosition<f64>,
+    /// Available camera controllers, the first one is active
+    camera_controllers: Vec<Box<dyn CameraController>>,
     camera_bind_group: wgpu::BindGroup,
     render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: VertexBuffer,
-    index_buffer: IndexBuffer,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    /// MSAA sample count of the render pipeline
+    sample_count: u32,
+    /// Multisampled color target resolved into the surface, `None` without MSAA
+    msaa_view: Option<wgpu::TextureView>,
+    /// Rasterization mode of the render pipeline, `Line` draws the wireframe
+    polygon_mode: wgpu::PolygonMode,
+    /// Faces culled by the render pipeline
+    cull_mode: CullMode,
+    #[cfg(feature = "hot-reload")]
+    shader_watcher: Option<ShaderWatcher>,
+    quad: Mesh,
     texture: Texture,
     img_texture: RgbaImage,
     scene: Scene,
+    /// Seed of the next generated stress test scene
+    scene_seed: u64,
+    /// Scene version, camera view projection and settings of the image in the target texture
+    rendered_state: Option<(u64, glam::Mat4, RenderSettings)>,
+    /// Trace resolution, reduced while the camera moves and restored once it stays still
+    resolution: InteractiveResolution,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
     diffuse_bind_group: wgpu::BindGroup,
+    debug_lines: DebugLines,
+    /// On-screen frame statistics, shown while the frame timing is enabled
+    text_overlay: TextOverlay,
+    /// Draw the ground grid and the world axes over the traced image
+    show_grid: bool,
+    /// Frame the whole scene again on every resize, otherwise only the aspect ratio changes
+    keep_framed: bool,
+    settings: RenderSettings,
+    /// Threads tracing the image, `None` thread count uses the global pool
+    tracer_pool: TracerPool,
 }
 
-fn create_target_texture(screen: &Screen) -> (RgbaImage, Texture) {
-    let mut img_data = Vec::with_capacity((IMG_WIDTH * IMG_HEIGHT * 4) as usize);
-    for _ in 0..(IMG_WIDTH * IMG_HEIGHT) {
-        for i in [234, 65, 123, 255] {
-            img_data.push(i);
-        }
-    }
-
-    let img_texture = image::RgbaImage::from_raw(IMG_WIDTH, IMG_HEIGHT, img_data).unwrap();
+/// Create the traced image and its texture, filled with `fill` until the first trace.
+fn create_target_texture(screen: &Screen, fill: Rgba<u8>) -> (RgbaImage, Texture) {
+    let img_texture = RgbaImage::from_pixel(IMG_WIDTH, IMG_HEIGHT, fill);
     let texture = Texture::from_image(
         &screen.device,
         &screen.queue,
@@ -52,23 +91,495 @@ fn create_target_texture(screen: &Screen) -> (RgbaImage, Texture) {
     (img_texture, texture)
 }
 
+fn create_diffuse_bind_group(
+    screen: &Screen,
+    layout: &wgpu::BindGroupLayout,
+    texture: &Texture,
+) -> wgpu::BindGroup {
+    screen.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            },
+        ],
+        label: Some("diffuse_bind_group"),
+    })
+}
+
+/// Create the multisampled color target matching the surface, `None` for a single sample.
+fn create_msaa_view(screen: &Screen, sample_count: u32) -> Option<wgpu::TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+
+    let texture = screen.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("MSAA color target"),
+        size: wgpu::Extent3d {
+            width: screen.config.width,
+            height: screen.config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: screen.format(),
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&TextureViewDescriptor::default()))
+}
+
+fn create_render_pipeline(
+    screen: &Screen,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    sample_count: u32,
+    polygon_mode: wgpu::PolygonMode,
+    cull_mode: CullMode,
+) -> wgpu::RenderPipeline {
+    screen
+        .device
+        .create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: screen.format(),
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: renderer::triangle_primitive_state(polygon_mode, cull_mode),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+}
+
+impl RayTracingCPU {
+    fn process_key(&mut self, keycode: VirtualKeyCode, screen: &mut Screen) {
+        match keycode {
+            VirtualKeyCode::F3 => screen.set_frame_timing(!screen.frame_timing_enabled()),
+            VirtualKeyCode::RBracket => {
+                self.settings.max_depth = (self.settings.max_depth + 1).min(MAX_BOUNCE_DEPTH);
+                tracing::info!("max bounce depth: {}", self.settings.max_depth);
+            }
+            VirtualKeyCode::LBracket => {
+                self.settings.max_depth = self.settings.max_depth.saturating_sub(1);
+                tracing::info!("max bounce depth: {}", self.settings.max_depth);
+            }
+            VirtualKeyCode::Equals => {
+                self.settings.exposure += EXPOSURE_STEP;
+                tracing::info!("exposure: {:+} EV", self.settings.exposure);
+            }
+            VirtualKeyCode::Minus => {
+                self.settings.exposure -= EXPOSURE_STEP;
+                tracing::info!("exposure: {:+} EV", self.settings.exposure);
+            }
+            VirtualKeyCode::N => {
+                let filter = match self.texture.filter() {
+                    wgpu::FilterMode::Linear => wgpu::FilterMode::Nearest,
+                    wgpu::FilterMode::Nearest => wgpu::FilterMode::Linear,
+                };
+                self.texture.set_filter(&screen.device, filter);
+                self.diffuse_bind_group = create_diffuse_bind_group(
+                    screen,
+                    &self.texture_bind_group_layout,
+                    &self.texture,
+                );
+                tracing::info!("display filter: {:?}", filter);
+            }
+            VirtualKeyCode::F => self.camera.frame_scene(&self.scene),
+            VirtualKeyCode::T => {
+                let thread_count = match self.tracer_pool.thread_count() {
+                    None => Some(1),
+                    Some(_) => None,
+                };
+                self.tracer_pool.set_thread_count(thread_count);
+                tracing::info!("ray tracer threads: {:?}", thread_count);
+            }
+            VirtualKeyCode::R => self.camera_controllers[0].reset(),
+            VirtualKeyCode::Tab => self.camera_controllers.rotate_left(1),
+            VirtualKeyCode::G => self.show_grid = !self.show_grid,
+            VirtualKeyCode::H => {
+                self.settings.heatmap = !self.settings.heatmap;
+                tracing::info!("ray count heatmap: {}", self.settings.heatmap);
+            }
+            VirtualKeyCode::V => {
+                self.keep_framed = !self.keep_framed;
+                tracing::info!("keep the scene framed on resize: {}", self.keep_framed);
+            }
+            VirtualKeyCode::Comma | VirtualKeyCode::Period => {
+                let projection = &mut self.camera.projection;
+                let factor = if keycode == VirtualKeyCode::Period {
+                    CLIP_PLANE_FACTOR
+                } else {
+                    CLIP_PLANE_FACTOR.recip()
+                };
+                projection.set_near(projection.near * factor);
+                tracing::info!("near plane: {}", projection.near);
+            }
+            VirtualKeyCode::Semicolon | VirtualKeyCode::Apostrophe => {
+                let projection = &mut self.camera.projection;
+                let factor = if keycode == VirtualKeyCode::Apostrophe {
+                    CLIP_PLANE_FACTOR
+                } else {
+                    CLIP_PLANE_FACTOR.recip()
+                };
+                projection.set_far(projection.far * factor);
+                tracing::info!("far plane: {}", projection.far);
+            }
+            VirtualKeyCode::M => self.cycle_sample_count(screen),
+            VirtualKeyCode::L => self.toggle_wireframe(screen),
+            VirtualKeyCode::B => self.cycle_cull_mode(screen),
+            VirtualKeyCode::F12 => self.save_screenshot(screen),
+            VirtualKeyCode::C => tracing::info!("camera pose:\n{}", self.camera.pose_literal()),
+            VirtualKeyCode::F5 => {
+                self.scene = Scene::random_spheres(
+                    STRESS_SCENE_SPHERES,
+                    self.scene_seed,
+                    STRESS_SCENE_BOUNDS,
+                );
+                self.scene_seed += 1;
+                tracing::info!("stress scene: {}", self.scene.stats());
+                self.camera.frame_scene(&self.scene);
+                self.settings.selected = None;
+                self.clear_target(screen, TARGET_FILL);
+            }
+            VirtualKeyCode::Delete => {
+                let selected = self.settings.selected.take();
+                if let Some(index) = selected.and_then(|handle| self.scene.index_of(handle)) {
+                    let sphere = self.scene.remove_sphere(index);
+                    tracing::info!("removed sphere {}: {:?}", index, sphere);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Switch to the next supported MSAA sample count, wrapping back to a single sample.
+    fn cycle_sample_count(&mut self, screen: &Screen) {
+        let counts = screen.supported_sample_counts(screen.format());
+        let next = counts
+            .iter()
+            .copied()
+            .find(|count| *count > self.sample_count)
+            .unwrap_or(1);
+
+        self.sample_count = next;
+        self.recreate_render_pipeline(screen);
+        self.msaa_view = create_msaa_view(screen, next);
+        self.debug_lines = DebugLines::new(&screen.device, screen.format(), next);
+        tracing::info!("MSAA sample count: {}", next);
+    }
+
+    /// Record the traced image quad and the debug lines into the render pass.
+    fn draw_scene<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        DrawCommand::new(&self.render_pipeline, &self.quad)
+            .bind_group(&self.diffuse_bind_group)
+            .bind_group(&self.camera_bind_group)
+            .execute(render_pass);
+
+        self.debug_lines.render(render_pass);
+    }
+
+    /// Render the scene into an off-screen target with the current sample count and save
+    /// it as a PNG image.
+    fn save_screenshot(&self, screen: &Screen) {
+        let usage = if self.sample_count > 1 {
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        } else {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC
+        };
+        let target = screen.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Screenshot target"),
+            size: wgpu::Extent3d {
+                width: screen.config.width,
+                height: screen.config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: self.sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: screen.format(),
+            usage,
+            view_formats: &[],
+        });
+        let view = target.create_view(&TextureViewDescriptor::default());
+
+        let mut encoder = screen
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Screenshot Encoder"),
+            });
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Screenshot Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: color_load_op(self.clear_color()),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.draw_scene(&mut render_pass);
+        }
+        screen.queue.submit(std::iter::once(encoder.finish()));
+
+        let Some(image) = renderer::capture_texture(&screen.device, &screen.queue, &target) else {
+            tracing::error!(
+                "screenshot unsupported for the {:?} format",
+                screen.format()
+            );
+            return;
+        };
+        match image.save(SCREENSHOT_PATH) {
+            Ok(()) => tracing::info!("screenshot saved to {}", SCREENSHOT_PATH),
+            Err(err) => tracing::error!("failed to save the screenshot: {}", err),
+        }
+    }
+
+    /// Switch between the filled and wireframe pipeline, wireframe requires the
+    /// `POLYGON_MODE_LINE` device feature.
+    fn toggle_wireframe(&mut self, screen: &Screen) {
+        let mode = match self.polygon_mode {
+            wgpu::PolygonMode::Fill => wgpu::PolygonMode::Line,
+            _ => wgpu::PolygonMode::Fill,
+        };
+        if mode == wgpu::PolygonMode::Line
+            && !screen
+                .device
+                .features()
+                .contains(wgpu::Features::POLYGON_MODE_LINE)
+        {
+            tracing::warn!("wireframe unavailable, POLYGON_MODE_LINE is not supported");
+            return;
+        }
+
+        self.polygon_mode = mode;
+        self.recreate_render_pipeline(screen);
+        tracing::info!("polygon mode: {:?}", mode);
+    }
+
+    fn cycle_cull_mode(&mut self, screen: &Screen) {
+        self.cull_mode = self.cull_mode.next();
+        self.recreate_render_pipeline(screen);
+        tracing::info!("cull mode: {:?}", self.cull_mode);
+    }
+
+    /// Rebuild the render pipeline from the current shader and pipeline settings.
+    fn recreate_render_pipeline(&mut self, screen: &Screen) {
+        self.render_pipeline = create_render_pipeline(
+            screen,
+            &self.render_pipeline_layout,
+            &self.shader,
+            self.sample_count,
+            self.polygon_mode,
+            self.cull_mode,
+        );
+    }
+
+    /// Fill the traced image with a solid color, forcing a new trace on the next update.
+    fn clear_target(&mut self, screen: &Screen, fill: Rgba<u8>) {
+        self.img_texture
+            .pixels_mut()
+            .for_each(|pixel| *pixel = fill);
+        self.texture.update_data(
+            &screen.queue,
+            &self.img_texture,
+            self.img_texture.width(),
+            self.img_texture.height(),
+        );
+        self.rendered_state = None;
+    }
+
+    /// Trace the scene into the target texture at `scale` times its resolution, upscaling
+    /// the reduced resolution images.
+    fn trace_image(&mut self, queue: &wgpu::Queue, scale: f32) {
+        let stats = if scale < 1.0 {
+            let width = ((IMG_WIDTH as f32 * scale) as u32).max(1);
+            let height = ((IMG_HEIGHT as f32 * scale) as u32).max(1);
+            let mut img = RgbaImage::new(width, height);
+            let stats = self
+                .tracer_pool
+                .install(|| render_image(&mut img, &self.scene, &self.camera, &self.settings));
+            self.img_texture =
+                image::imageops::resize(&img, IMG_WIDTH, IMG_HEIGHT, FilterType::Nearest);
+            self.texture
+                .update_data(queue, &self.img_texture, IMG_WIDTH, IMG_HEIGHT);
+            stats
+        } else {
+            self.tracer_pool.install(|| {
+                render_to_texture(
+                    &mut self.img_texture,
+                    &self.texture,
+                    &self.scene,
+                    &self.camera,
+                    queue,
+                    &self.settings,
+                )
+            })
+        };
+        tracing::debug!(
+            scale,
+            primary_rays = stats.primary_rays,
+            rays_cast = stats.rays_cast,
+            hits = stats.hits,
+            shadow_rays = stats.shadow_rays,
+            "traced image"
+        );
+    }
+
+    /// Select the sphere under the cursor.
+    fn pick_sphere(&mut self, screen: &Screen) {
+        // The traced image covers the whole window with its first row at the bottom.
+        let u = self.cursor_position.x as f32 / screen.config.width as f32;
+        let v = 1.0 - self.cursor_position.y as f32 / screen.config.height as f32;
+        let px = ((u * IMG_WIDTH as f32) as u32).min(IMG_WIDTH - 1);
+        let py = ((v * IMG_HEIGHT as f32) as u32).min(IMG_HEIGHT - 1);
+        let view_y = self.settings.view_row(py, IMG_HEIGHT);
+
+        let ray = self
+            .camera
+            .ray_through_pixel(px, view_y, IMG_WIDTH, IMG_HEIGHT);
+        let picked = self.scene.pick(&ray);
+        self.settings.selected = picked.and_then(|index| self.scene.handle(index));
+        match picked {
+            Some(index) => {
+                tracing::info!(
+                    "selected sphere {}: {:?}",
+                    index,
+                    self.scene.spheres()[index]
+                )
+            }
+            None => tracing::info!("no sphere selected"),
+        }
+    }
+}
+
+#[cfg(feature = "hot-reload")]
+const SHADER_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/src/asset/shader/basic_shape.wgsl"
+);
+
+/// Apply the edit bound to the key to the selected sphere, returns whether the key moved
+/// or resized a sphere.
+fn edit_selected(
+    scene: &mut Scene,
+    selected: Option<SphereHandle>,
+    keycode: VirtualKeyCode,
+) -> bool {
+    let (offset, radius_change) = match keycode {
+        VirtualKeyCode::Left => (Vec3::NEG_X * NUDGE_STEP, 0.0),
+        VirtualKeyCode::Right => (Vec3::X * NUDGE_STEP, 0.0),
+        VirtualKeyCode::Up => (Vec3::Y * NUDGE_STEP, 0.0),
+        VirtualKeyCode::Down => (Vec3::NEG_Y * NUDGE_STEP, 0.0),
+        VirtualKeyCode::I => (Vec3::NEG_Z * NUDGE_STEP, 0.0),
+        VirtualKeyCode::K => (Vec3::Z * NUDGE_STEP, 0.0),
+        VirtualKeyCode::NumpadAdd => (Vec3::ZERO, RADIUS_STEP),
+        VirtualKeyCode::NumpadSubtract => (Vec3::ZERO, -RADIUS_STEP),
+        _ => return false,
+    };
+    let Some(sphere) = selected
+        .and_then(|handle| scene.index_of(handle))
+        .and_then(|index| scene.sphere_mut(index))
+    else {
+        return false;
+    };
+    sphere.position += offset;
+    sphere.radius = (sphere.radius + radius_change).max(MIN_SPHERE_RADIUS);
+    true
+}
+
+#[cfg(feature = "hot-reload")]
+impl RayTracingCPU {
+    /// Recreate the render pipeline when the shader file changes, keeping the current
+    /// pipeline if the new shader fails to compile.
+    fn reload_shader(&mut self, screen: &Screen) {
+        let changed = self
+            .shader_watcher
+            .as_ref()
+            .and_then(ShaderWatcher::poll_changed)
+            .is_some();
+        if !changed {
+            return;
+        }
+
+        let source = match shader::load_file(std::path::Path::new(SHADER_PATH)) {
+            Ok(source) => source,
+            Err(err) => {
+                tracing::error!("failed to reload shader: {}", err);
+                return;
+            }
+        };
+        let shader = match screen.create_shader_checked(&source) {
+            Ok(shader) => shader,
+            Err(err) => {
+                tracing::error!("failed to reload shader: {}", err);
+                return;
+            }
+        };
+
+        screen
+            .device
+            .push_error_scope(wgpu::ErrorFilter::Validation);
+        let render_pipeline = create_render_pipeline(
+            screen,
+            &self.render_pipeline_layout,
+            &shader,
+            self.sample_count,
+            self.polygon_mode,
+            self.cull_mode,
+        );
+
+        match pollster::block_on(screen.device.pop_error_scope()) {
+            Some(err) => tracing::error!("failed to reload shader: {}", err),
+            None => {
+                tracing::info!("shader reloaded");
+                self.render_pipeline = render_pipeline;
+                self.shader = shader;
+            }
+        }
+    }
+}
+
 impl Layer for RayTracingCPU {
     type LayerErr = ();
 
     fn start(screen: &mut Screen, _app: &AppState) -> Self {
+        let source = shader::load_embedded("basic_shape.wgsl")
+            .unwrap_or_else(|err| panic!("Invalid shader basic_shape.wgsl: {}", err));
         let shader = screen
-            .device
-            .create_shader_module(include_wgsl!("asset/shader/basic_shape.wgsl"));
+            .create_shader_checked(&source)
+            .unwrap_or_else(|err| panic!("Invalid shader basic_shape.wgsl: {}", err));
 
-        let vertex_buffer = VertexBuffer::init_immediate(
-            &screen.device,
-            bytemuck::cast_slice(QUAD_VERTICES),
-            Some("Vertex Buffer"),
-        );
+        let vertex_buffer =
+            VertexBuffer::init_immediate(&screen.device, QUAD_VERTICES, Some("Vertex Buffer"));
         let index_buffer =
             IndexBuffer::init_immediate_u16(&screen.device, QUAD_INDICES, Some("Index Buffer"));
 
-        let (img_texture, texture) = create_target_texture(screen);
+        let (img_texture, texture) = create_target_texture(screen, TARGET_FILL);
 
         let texture_bind_group_layout =
             screen
@@ -95,20 +606,8 @@ impl Layer for RayTracingCPU {
                     label: Some("texture_bind_group_layout"),
                 });
 
-        let diffuse_bind_group = screen.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
-                },
-            ],
-            label: Some("diffuse_bind_group"),
-        });
+        let diffuse_bind_group =
+            create_diffuse_bind_group(screen, &texture_bind_group_layout, &texture);
 
         let camera = Camera::default();
 
@@ -116,7 +615,7 @@ impl Layer for RayTracingCPU {
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Camera Buffer"),
-                contents: bytemuck::cast_slice(&[camera.view_projection()]),
+                contents: bytemuck::cast_slice(&[DISPLAY_TRANSFORM]),
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
 
@@ -155,240 +654,347 @@ impl Layer for RayTracingCPU {
                     push_constant_ranges: &[],
                 });
 
-        let render_pipeline = screen
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[Vertex::layout()],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: screen.config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-            });
+        let sample_count = screen.sample_count();
+        let render_pipeline = create_render_pipeline(
+            screen,
+            &render_pipeline_layout,
+            &shader,
+            sample_count,
+            wgpu::PolygonMode::Fill,
+            QUAD_CULL_MODE,
+        );
 
-        let scene = Scene {
-            spheres: vec![
-                Sphere {
-                    albedo: Vec3::new(1.0, 0.0, 1.0),
-                    radius: 0.5,
-                    position: Vec3::ZERO,
-                },
-                Sphere {
-                    albedo: Vec3::new(0.2, 0.3, 1.0),
-                    radius: 1.5,
-                    position: Vec3::new(1.0, 0.0, -5.0),
-                },
-            ],
-        };
+        let scene = std::env::args()
+            .nth(1)
+            .and_then(|path| load_scene(std::path::Path::new(&path)))
+            .unwrap_or_else(default_scene);
 
         Self {
             camera,
-            camera_controller: CameraController::new(0.2),
-            camera_buffer,
+            camera_controllers: vec![
+                Box::new(FreeFlyController::new(2.0, 0.002)),
+                Box::new(DollyController::new(2.0)),
+            ],
+            mouse_look: false,
+            cursor_position: PhysicalPosition::default(),
             camera_bind_group,
             render_pipeline,
-            vertex_buffer,
-            index_buffer,
+            render_pipeline_layout,
+            shader,
+            sample_count,
+            msaa_view: create_msaa_view(screen, sample_count),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            cull_mode: QUAD_CULL_MODE,
+            #[cfg(feature = "hot-reload")]
+            shader_watcher: ShaderWatcher::new(SHADER_PATH)
+                .map_err(|err| tracing::warn!("shader hot-reload disabled: {}", err))
+                .ok(),
+            quad: Mesh::new(vertex_buffer, index_buffer),
             texture,
             img_texture,
             scene,
+            scene_seed: 0,
+            rendered_state: None,
+            resolution: InteractiveResolution::default(),
+            texture_bind_group_layout,
             diffuse_bind_group,
+            debug_lines: DebugLines::new(&screen.device, screen.format(), sample_count),
+            text_overlay: TextOverlay::new(&screen.device, &screen.queue, screen.format()),
+            show_grid: false,
+            keep_framed: false,
+            settings: RenderSettings::default(),
+            tracer_pool: TracerPool::default(),
         }
     }
 
-    fn resize(&mut self, new_size: PhysicalSize<u32>, _state: &AppState, _screen: &mut Screen) {
-        self.camera.projection.aspect_ratio = new_size.width as f32 / new_size.height as f32;
+    fn resize(&mut self, new_size: PhysicalSize<u32>, _state: &AppState, screen: &mut Screen) {
+        self.camera
+            .resize(new_size, self.keep_framed.then_some(&self.scene));
+        self.msaa_view = create_msaa_view(screen, self.sample_count);
     }
 
-    fn process_event(&mut self, event: &Event<()>, _screen: &mut Screen) {
+    fn process_event(&mut self, event: &Event<()>, screen: &mut Screen) -> bool {
         match event {
             Event::WindowEvent { ref event, .. } => {
-                self.camera_controller
-                    .process_events(&mut self.camera, event, 1.0);
+                match event {
+                    WindowEvent::MouseInput {
+                        button: MouseButton::Right,
+                        state,
+                        ..
+                    } => {
+                        self.mouse_look = *state == ElementState::Pressed;
+                        screen.set_cursor_grab(self.mouse_look);
+                    }
+                    WindowEvent::MouseInput {
+                        button: MouseButton::Left,
+                        state: ElementState::Pressed,
+                        ..
+                    } => self.pick_sphere(screen),
+                    WindowEvent::CursorMoved { position, .. } => {
+                        self.cursor_position = *position;
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(keycode),
+                                ..
+                            },
+                        ..
+                    } => {
+                        // Keys editing the selected sphere are not forwarded to the camera.
+                        if edit_selected(&mut self.scene, self.settings.selected, *keycode) {
+                            return true;
+                        }
+                        self.process_key(*keycode, screen);
+                    }
+                    _ => {}
+                }
+
+                self.camera_controllers[0].process_event(event, screen.actions());
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } if self.mouse_look => {
+                self.camera_controllers[0].process_mouse_motion(*delta);
             }
             _ => {}
         }
+        false
     }
 
-    fn update(&mut self, _app: &AppState, screen: &mut Screen) {
-        screen.queue.write_buffer(
-            &self.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera.view_projection()]),
-        );
+    fn update(&mut self, app: &AppState, screen: &mut Screen) {
+        #[cfg(feature = "hot-reload")]
+        self.reload_shader(screen);
 
-        render_to_texture(
-            &mut self.img_texture,
-            &self.texture,
-            &mut self.scene,
-            &screen.queue,
+        self.camera_controllers[0].process_gamepad(app.gamepad());
+        self.camera_controllers[0].update(&mut self.camera, app.elapsed_time());
+        let state = (
+            self.scene.version(),
+            self.camera.view_projection(),
+            self.settings,
         );
+        let camera_moved = self
+            .rendered_state
+            .is_some_and(|(_, view_projection, _)| view_projection != state.1);
+        let stale = self.rendered_state != Some(state);
+        if let Some(scale) = self.resolution.update(camera_moved, stale) {
+            self.trace_image(&screen.queue, scale);
+            self.rendered_state = Some(state);
+        }
     }
 
-    fn render(&mut self, _app: &AppState, screen: &mut Screen) -> Result<(), wgpu::SurfaceError> {
-        let output = screen.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&TextureViewDescriptor::default());
+    fn render(
+        &mut self,
+        app: &AppState,
+        screen: &mut Screen,
+        frame: &mut Frame,
+    ) -> Result<(), wgpu::SurfaceError> {
+        let view = frame.view();
         let mut encoder = screen
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
 
+        if self.show_grid {
+            let lines = &mut self.debug_lines.lines;
+            lines.draw_grid(GRID_SPACING, GRID_EXTENT, GRID_COLOR);
+            lines.draw_axes(GRID_SPACING);
+        }
+        self.debug_lines
+            .prepare(&screen.device, &screen.queue, self.camera.view_projection());
+
+        if screen.frame_timing_enabled() && app.elapsed_time() > 0.0 {
+            let frame_time = app.elapsed_time();
+            self.text_overlay.text.draw_text(
+                TEXT_MARGIN,
+                TEXT_MARGIN,
+                &format!(
+                    "{:.0} FPS ({:.2} MS)\n{}",
+                    frame_time.recip(),
+                    frame_time * 1000.0,
+                    self.scene.stats()
+                ),
+            );
+        }
+        self.text_overlay.prepare(
+            &screen.device,
+            &screen.queue,
+            screen.config.width,
+            screen.config.height,
+        );
+
+        screen.gpu_timer.begin(&mut encoder);
         {
             let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: self.msaa_view.as_ref().unwrap_or(view),
+                    resolve_target: self.msaa_view.as_ref().map(|_| view),
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
+                        load: color_load_op(self.clear_color()),
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.buffer().slice(..));
-            render_pass.set_index_buffer(
-                self.index_buffer.buffer().slice(..),
-                self.index_buffer.format(),
-            );
-            render_pass.draw_indexed(0..self.index_buffer.count(), 0, 0..1);
+            self.draw_scene(&mut render_pass);
+        }
+        {
+            // Drawn on the resolved frame, after every other pass.
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Text Overlay Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.text_overlay.render(&mut render_pass);
         }
 
-        screen.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        screen.gpu_timer.end(&mut encoder);
+
+        frame.submit(encoder.finish());
 
         Ok(())
     }
 
+    fn clear_color(&self) -> Option<wgpu::Color> {
+        Some(wgpu::Color {
+            r: 0.1,
+            g: 0.2,
+            b: 0.3,
+            a: 1.0,
+        })
+    }
+
     fn shutdown(&mut self, _app: &AppState, _screen: &mut Screen) -> Result<(), Self::LayerErr> {
         tracing::info!("exiting");
         Ok(())
     }
 }
 
+/// Scale the unit quad to cover the whole screen
+const DISPLAY_TRANSFORM: glam::Mat4 = glam::Mat4::from_cols(
+    glam::Vec4::new(2.0, 0.0, 0.0, 0.0),
+    glam::Vec4::new(0.0, 2.0, 0.0, 0.0),
+    glam::Vec4::new(0.0, 0.0, 1.0, 0.0),
+    glam::Vec4::new(0.0, 0.0, 0.0, 1.0),
+);
+
 const IMG_WIDTH: u32 = 800;
 const IMG_HEIGHT: u32 = 800;
+/// Color of the traced image before the first trace
+const TARGET_FILL: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// Number of spheres in the generated stress test scene
+const STRESS_SCENE_SPHERES: usize = 100;
+/// Region filled by the generated stress test scene
+const STRESS_SCENE_BOUNDS: Aabb = Aabb {
+    min: glam::Vec3::new(-5.0, -5.0, -10.0),
+    max: glam::Vec3::new(5.0, 5.0, 0.0),
+};
 
-fn render_to_texture(img: &mut RgbaImage, texture: &Texture, scene: &Scene, queue: &wgpu::Queue) {
-    let mut ray = Ray {
-        origin: glam::Vec3::new(0.0, 0.0, 2.0),
-        direction: glam::Vec3::ZERO,
-    };
-    for y in 0..IMG_HEIGHT {
-        for x in 0..IMG_WIDTH {
-            let coord = glam::Vec2::new(x as f32 / IMG_WIDTH as f32, y as f32 / IMG_HEIGHT as f32)
-                * 2.0
-                - 1.0;
-            ray.direction = glam::Vec3::new(coord.x, coord.y, -1.0);
-            let color = cast_ray(scene, &ray);
-            img.put_pixel(x, y, Rgba(convert_rgba(color)));
-        }
-    }
-
-    texture.update_data(queue, &img, IMG_WIDTH, IMG_HEIGHT);
+/// Exposure change in stops for each key press
+const EXPOSURE_STEP: f32 = 0.5;
+/// Distance the selected sphere is moved for each key press
+const NUDGE_STEP: f32 = 0.1;
+/// Radius change of the selected sphere for each key press
+const RADIUS_STEP: f32 = 0.05;
+const MIN_SPHERE_RADIUS: f32 = 0.05;
+/// Scale applied to the near or far plane distance for each key press
+const CLIP_PLANE_FACTOR: f32 = 2.0;
+
+/// Distance between the ground grid lines
+const GRID_SPACING: f32 = 1.0;
+/// Distance from the origin covered by the ground grid
+const GRID_EXTENT: f32 = 10.0;
+const GRID_COLOR: glam::Vec4 = glam::Vec4::new(0.6, 0.6, 0.6, 0.5);
+
+/// Distance of the overlay text from the top left corner of the window (pixels)
+const TEXT_MARGIN: f32 = 8.0;
+
+/// File written by the screenshot key, relative to the working directory
+const SCREENSHOT_PATH: &str = "screenshot.png";
+
+/// Scene shown when no scene file is given on the command line
+fn default_scene() -> Scene {
+    let mut scene = Scene::default();
+    scene.add_sphere(Sphere {
+        albedo: Vec3::new(1.0, 0.0, 1.0),
+        radius: 0.5,
+        position: Vec3::ZERO,
+        ..Default::default()
+    });
+    scene.add_sphere(Sphere {
+        albedo: Vec3::new(0.2, 0.3, 1.0),
+        radius: 1.5,
+        position: Vec3::new(1.0, 0.0, -5.0),
+        metallic: true,
+        ..Default::default()
+    });
+    scene.add_light(PointLight {
+        position: Vec3::new(4.0, 4.0, 4.0),
+        radius: 0.5,
+        ..Default::default()
+    });
+    scene
 }
 
-fn convert_rgba(color: glam::Vec4) -> [u8; 4] {
-    let r = (color.x * 255.0) as u8;
-    let g = (color.y * 255.0) as u8;
-    let b = (color.z * 255.0) as u8;
-    let a = (color.w * 255.0) as u8;
-    [r, g, b, a]
+/// Read a scene file in the [`Scene::parse`] format, logging the failures.
+fn load_scene(path: &std::path::Path) -> Option<Scene> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| tracing::error!("failed to read the scene {}: {}", path.display(), err))
+        .ok()?;
+    Scene::parse(&source)
+        .map_err(|err| tracing::error!("invalid scene {}: {}", path.display(), err))
+        .ok()
 }
 
-fn cast_ray(scene: &Scene, ray: &Ray) -> glam::Vec4 {
-    // (bx^2 + by^2 + bz^2)t^2 + (2(axbx + ayby + azbz))t + (ax^2 + ay^2 + az^2 - r^2) = 0
-    // where
-    // a = ray origin
-    // b = ray direction
-    // r = radius
-    // t = hit distance
+fn main() {
+    tracing_subscriber::fmt::init();
+    let builder = ApplicationBuilder::new().window(WindowBuilder::new().with_title("rust-wgpu"));
+    pollster::block_on(builder.run::<RayTracingCPU>());
+}
 
-    let clear_color = glam::Vec4::new(0.0, 0.0, 0.0, 1.0);
-    let light_direction = glam::Vec3::new(-1.0, -1.0, -1.0).normalize();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translate_key_moves_only_the_selected_sphere() {
+        let mut scene = Scene::default();
+        let first = scene.add_sphere(Sphere::default());
+        let second = scene.add_sphere(Sphere {
+            position: Vec3::new(1.0, 0.0, -5.0),
+            ..Default::default()
+        });
 
-    if scene.spheres.is_empty() {
-        return clear_color;
+        assert!(edit_selected(
+            &mut scene,
+            Some(second),
+            VirtualKeyCode::Right
+        ));
+        let moved = scene.get(second).unwrap().position;
+        assert!(moved.abs_diff_eq(Vec3::new(1.0 + NUDGE_STEP, 0.0, -5.0), 1e-6));
+        assert_eq!(scene.get(first).unwrap().position, Vec3::ZERO);
     }
 
-    let mut closest_sphere: Option<&Sphere> = None;
-    let mut hit_distance = std::f32::MAX;
-
-    for sphere in &scene.spheres {
-        let origin = ray.origin - sphere.position;
+    #[test]
+    fn edit_keys_need_a_selection() {
+        let mut scene = Scene::default();
+        let handle = scene.add_sphere(Sphere::default());
 
-        let a = glam::Vec3::dot(ray.direction, ray.direction);
-        let b = 2.0 * glam::Vec3::dot(origin, ray.direction);
-        let c = glam::Vec3::dot(origin, origin) - sphere.radius * sphere.radius;
-
-        let discriminant = b * b - 4.0 * a * c;
-        if discriminant < 0.0 {
-            continue;
-        }
-
-        let closest_t = (-b - discriminant.sqrt()) / (2.0 * a);
-
-        if closest_t < hit_distance {
-            hit_distance = closest_t;
-            closest_sphere = Some(&sphere);
-        }
+        assert!(!edit_selected(&mut scene, None, VirtualKeyCode::Right));
+        assert!(!edit_selected(&mut scene, Some(handle), VirtualKeyCode::W));
+        assert_eq!(scene.get(handle).unwrap().position, Vec3::ZERO);
     }
-
-    if closest_sphere.is_none() {
-        return clear_color;
-    }
-
-    let sphere = closest_sphere.unwrap();
-
-    let origin = ray.origin - sphere.position;
-    let hit_point = origin + ray.direction * hit_distance;
-    let normal = hit_point.normalize();
-
-    let intensity = normal.dot(-light_direction).max(0.0); // == cos(angle)
-
-    let sphere_color = sphere.albedo * intensity;
-    return glam::Vec4::new(sphere_color.x, sphere_color.y, sphere_color.z, 1.0);
-}
-
-fn main() {
-    tracing_subscriber::fmt::init();
-    pollster::block_on(Application::<RayTracingCPU>::init());
 }
@@ -0,0 +1,313 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::renderer;
+
+/// Number of timestamps written per frame (begin and end of the measured scope).
+const TIMESTAMP_COUNT: u32 = 2;
+const TIMESTAMP_BUFFER_SIZE: u64 = TIMESTAMP_COUNT as u64 * std::mem::size_of::<u64>() as u64;
+
+struct TimestampQuery {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+}
+
+/// Progress of the timestamps through the readback buffer
+enum Readback {
+    /// The readback buffer is free for a new measurement
+    Idle,
+    /// The copy to the readback buffer was recorded, the buffer is mapped once submitted
+    Recorded,
+    /// The readback buffer is being mapped, the flag is set once the mapping completes
+    Mapping(Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>),
+}
+
+/// Measures the GPU time spent between [`GpuTimer::begin`] and [`GpuTimer::end`].
+///
+/// Requires the [`wgpu::Features::TIMESTAMP_QUERY`] feature, when the device does not
+/// support it every method is a no-op and the measurement is always `None`. Nothing is
+/// measured until the timer is enabled.
+///
+/// The timestamps are read back without blocking, frames recorded while the previous
+/// measurement is still being read are not measured.
+pub struct GpuTimer {
+    query: Option<TimestampQuery>,
+    enabled: bool,
+    /// The begin timestamp was written and the end timestamp is expected
+    measuring: bool,
+    readback: Readback,
+    last_ms: Option<f32>,
+}
+
+impl GpuTimer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return Self::disabled();
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("GPU timer query set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: TIMESTAMP_COUNT,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU timer resolve buffer"),
+            size: TIMESTAMP_BUFFER_SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU timer readback buffer"),
+            size: TIMESTAMP_BUFFER_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            query: Some(TimestampQuery {
+                query_set,
+                resolve_buffer,
+                readback_buffer,
+                period: queue.get_timestamp_period(),
+            }),
+            ..Self::disabled()
+        }
+    }
+
+    /// Create a timer that never measures anything.
+    pub fn disabled() -> Self {
+        Self {
+            query: None,
+            enabled: false,
+            measuring: false,
+            readback: Readback::Idle,
+            last_ms: None,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.query.is_some()
+    }
+
+    /// Start or stop measuring, the last measurement is discarded when stopped.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.last_ms = None;
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Write the starting timestamp, must be called before the measured pass.
+    pub fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.measuring = false;
+        if !self.enabled || !matches!(self.readback, Readback::Idle) {
+            return;
+        }
+        if let Some(query) = &self.query {
+            encoder.write_timestamp(&query.query_set, 0);
+            self.measuring = true;
+        }
+    }
+
+    /// Write the ending timestamp and copy the results to the readback buffer.
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !std::mem::take(&mut self.measuring) {
+            return;
+        }
+        if let Some(query) = &self.query {
+            encoder.write_timestamp(&query.query_set, 1);
+            encoder.resolve_query_set(
+                &query.query_set,
+                0..TIMESTAMP_COUNT,
+                &query.resolve_buffer,
+                0,
+            );
+            encoder.copy_buffer_to_buffer(
+                &query.resolve_buffer,
+                0,
+                &query.readback_buffer,
+                0,
+                TIMESTAMP_BUFFER_SIZE,
+            );
+            self.readback = Readback::Recorded;
+        }
+    }
+
+    /// Advance the readback of the submitted measurements without blocking, returning the
+    /// last completed measurement.
+    ///
+    /// Must be called after the command buffer containing [`GpuTimer::end`] was submitted.
+    pub fn collect(&mut self, device: &wgpu::Device) -> Option<f32> {
+        let Some(query) = &self.query else {
+            return None;
+        };
+
+        if let Readback::Recorded = self.readback {
+            let status = Arc::new(Mutex::new(None));
+            let callback_status = status.clone();
+            query
+                .readback_buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    *callback_status.lock().unwrap() = Some(result);
+                });
+            self.readback = Readback::Mapping(status);
+        }
+
+        let Readback::Mapping(status) = &self.readback else {
+            return self.last_ms;
+        };
+        renderer::poll(device);
+        let Some(result) = status.lock().unwrap().take() else {
+            return self.last_ms;
+        };
+        self.readback = Readback::Idle;
+
+        match result {
+            Ok(()) => {
+                let elapsed_ticks = {
+                    let data = query.readback_buffer.slice(..).get_mapped_range();
+                    let timestamps: &[u64] = bytemuck::cast_slice(&data);
+                    timestamps[1].wrapping_sub(timestamps[0])
+                };
+                query.readback_buffer.unmap();
+                if self.enabled {
+                    self.last_ms = Some(elapsed_ticks as f32 * query.period / 1_000_000.0);
+                }
+            }
+            Err(err) => tracing::warn!("failed to read the GPU timestamps: {}", err),
+        }
+        self.last_ms
+    }
+
+    /// Last measured GPU time in milliseconds.
+    pub fn last_ms(&self) -> Option<f32> {
+        self.last_ms
+    }
+}
+
+/// CPU and GPU time spent on a single frame.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameTimings {
+    /// CPU time spent updating the layer
+    pub update: Duration,
+    /// CPU time spent encoding and submitting the layer render
+    pub render: Duration,
+    /// GPU render time in milliseconds, when timestamp queries are supported
+    pub gpu_ms: Option<f32>,
+}
+
+impl FrameTimings {
+    pub fn log(&self) {
+        tracing::debug!(
+            update_ms = self.update.as_secs_f32() * 1000.0,
+            render_ms = self.render.as_secs_f32() * 1000.0,
+            gpu_ms = ?self.gpu_ms,
+            "frame timings"
+        );
+    }
+}
+
+/// Records the [`FrameTimings`] of the frames while enabled, off by default.
+#[derive(Debug, Default)]
+pub struct FrameProfiler {
+    enabled: bool,
+    /// Timings of the last frame, `None` while disabled
+    last: Option<FrameTimings>,
+}
+
+impl FrameProfiler {
+    /// Start or stop recording, the last timings are discarded when stopped.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.last = None;
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Record and log the timings of a frame whose update started at `update_start` and
+    /// render at `render_start`, ending now.
+    pub fn record(&mut self, update_start: Instant, render_start: Instant, gpu_ms: Option<f32>) {
+        if !self.enabled {
+            return;
+        }
+        let timings = FrameTimings {
+            update: render_start.saturating_duration_since(update_start),
+            render: render_start.elapsed(),
+            gpu_ms,
+        };
+        timings.log();
+        self.last = Some(timings);
+    }
+
+    /// Timings of the last recorded frame.
+    pub fn last(&self) -> Option<FrameTimings> {
+        self.last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::test_device;
+
+    #[test]
+    fn disabled_timer_measures_nothing() {
+        let timer = GpuTimer::disabled();
+        assert!(!timer.is_supported());
+        assert_eq!(timer.last_ms(), None);
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn timer_without_timestamp_queries_reports_none() {
+        // The test device is requested without any feature, TIMESTAMP_QUERY included
+        let (device, queue) = test_device();
+        let mut timer = GpuTimer::new(&device, &queue);
+        assert!(!timer.is_supported());
+        timer.set_enabled(true);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        timer.begin(&mut encoder);
+        timer.end(&mut encoder);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        assert_eq!(timer.collect(&device), None);
+        assert_eq!(timer.last_ms(), None);
+    }
+
+    #[test]
+    fn enabled_profiler_records_the_frame_timings() {
+        let mut profiler = FrameProfiler::default();
+        let start = Instant::now();
+        profiler.record(start, start, None);
+        assert!(profiler.last().is_none());
+
+        profiler.set_enabled(true);
+        let update_start = Instant::now();
+        std::thread::sleep(Duration::from_millis(2));
+        let render_start = Instant::now();
+        profiler.record(update_start, render_start, Some(1.5));
+
+        let timings = profiler.last().unwrap();
+        assert!(timings.update >= Duration::from_millis(2));
+        assert!(timings.render <= update_start.elapsed());
+        assert_eq!(timings.gpu_ms, Some(1.5));
+
+        profiler.set_enabled(false);
+        assert!(profiler.last().is_none());
+    }
+}
@@ -1,4 +0,0 @@
-pub struct Ray {
-    pub origin: glam::Vec3,
-    pub direction: glam::Vec3,
-}
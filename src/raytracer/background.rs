@@ -0,0 +1,107 @@
+use image::RgbaImage;
+
+use super::scene::sphere_uv;
+
+/// Color returned by the rays that miss every object in the scene
+#[derive(Debug, Clone)]
+pub enum Background {
+    Color(glam::Vec3),
+    Environment(EnvMap),
+}
+
+impl Background {
+    pub fn sample(&self, direction: glam::Vec3) -> glam::Vec3 {
+        match self {
+            Self::Color(color) => *color,
+            Self::Environment(env_map) => env_map.sample(direction),
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::Color(glam::Vec3::ZERO)
+    }
+}
+
+/// Equirectangular environment image, mapped with the same spherical coordinates as
+/// [`sphere_uv`].
+#[derive(Debug, Clone)]
+pub struct EnvMap {
+    image: RgbaImage,
+}
+
+impl EnvMap {
+    pub fn new(image: RgbaImage) -> Self {
+        Self { image }
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    /// Bilinearly sample the environment in the direction, which must be normalized.
+    ///
+    /// Samples wrap horizontally across the `u` seam and are clamped at the poles.
+    pub fn sample(&self, direction: glam::Vec3) -> glam::Vec3 {
+        let (width, height) = self.image.dimensions();
+        if width == 0 || height == 0 {
+            return glam::Vec3::ZERO;
+        }
+
+        let uv = sphere_uv(direction);
+        // Texel centers are at half integer coordinates.
+        let x = uv.x * width as f32 - 0.5;
+        let y = uv.y * height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let column = |x: f32| (x as i64).rem_euclid(width as i64) as u32;
+        let row = |y: f32| (y as i64).clamp(0, height as i64 - 1) as u32;
+        let texel = |x: u32, y: u32| {
+            let [r, g, b, _] = self.image.get_pixel(x, y).0;
+            glam::Vec3::new(r as f32, g as f32, b as f32) / 255.0
+        };
+
+        let (x0, x1) = (column(x0), column(x0 + 1.0));
+        let (y0, y1) = (row(y0), row(y0 + 1.0));
+        let top = texel(x0, y0).lerp(texel(x1, y0), fx);
+        let bottom = texel(x0, y1).lerp(texel(x1, y1), fx);
+        top.lerp(bottom, fy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn solid_environment_returns_its_color() {
+        let env_map = EnvMap::new(RgbaImage::from_pixel(8, 4, Rgba([255, 128, 0, 255])));
+        let expected = glam::Vec3::new(1.0, 128.0 / 255.0, 0.0);
+
+        for direction in [
+            glam::Vec3::X,
+            glam::Vec3::NEG_Y,
+            glam::Vec3::new(1.0, 1.0, -1.0),
+        ] {
+            let color = env_map.sample(direction.normalize());
+            assert!((color - expected).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn sampling_is_continuous_across_the_seam() {
+        // Horizontal gradient, the first and last columns differ the most.
+        let env_map = EnvMap::new(RgbaImage::from_fn(16, 4, |x, _| {
+            Rgba([(x * 16) as u8, 0, 0, 255])
+        }));
+
+        // The seam is at the -X direction.
+        let before = env_map.sample(glam::Vec3::new(-1.0, 0.0, 1e-3).normalize());
+        let after = env_map.sample(glam::Vec3::new(-1.0, 0.0, -1e-3).normalize());
+        assert!((before - after).length() < 0.01);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::util::rng::Rng;
+
+/// Light emitted from a point, or from a sphere when the radius is positive.
+///
+/// Spherical lights cast soft shadows, the light has no distance falloff.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    pub position: glam::Vec3,
+    /// Radiance reaching the lit surfaces
+    pub color: glam::Vec3,
+    /// Radius of the emitting sphere, `0.0` is a point light with hard shadows
+    pub radius: f32,
+}
+
+impl PointLight {
+    /// Point of the light a shadow ray is traced toward, uniformly distributed inside the
+    /// emitting sphere.
+    pub fn sample_point(&self, rng: &mut Rng) -> glam::Vec3 {
+        if self.radius > 0.0 {
+            self.position + rng.in_unit_sphere() * self.radius
+        } else {
+            self.position
+        }
+    }
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            position: glam::Vec3::ZERO,
+            color: glam::Vec3::ONE,
+            radius: 0.0,
+        }
+    }
+}
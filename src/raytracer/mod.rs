@@ -0,0 +1,1210 @@
+use image::{Rgba, RgbaImage};
+use rayon::prelude::*;
+
+use crate::{
+    camera::Camera,
+    texture::Texture,
+    util::{
+        math::{halton, sanitize_color},
+        rng::Rng,
+    },
+};
+
+use self::{
+    light::PointLight,
+    ray::{Hit, Ray},
+    scene::{Scene, SphereHandle},
+};
+
+pub mod background;
+pub mod light;
+pub mod ray;
+pub mod scene;
+
+/// Color of the selected sphere outline
+const SELECTION_COLOR: glam::Vec4 = glam::Vec4::new(1.0, 0.6, 0.0, 1.0);
+/// Cosine between the normal and the view ray below which the selected sphere is outlined
+const SELECTION_OUTLINE: f32 = 0.3;
+/// Weight of the selection color blended over the shading of the selected sphere
+const SELECTION_TINT: f32 = 0.25;
+
+/// Upper bound of the reflection bounces
+pub const MAX_BOUNCE_DEPTH: u32 = 8;
+/// Fraction of the light carried by each reflection bounce
+const REFLECTION_FACTOR: f32 = 0.5;
+/// Offset applied to the reflected ray origin to avoid hitting the same surface
+const HIT_BIAS: f32 = 1e-4;
+/// Direction of the light of the scenes without lights, which casts no shadow
+const DEFAULT_LIGHT_DIRECTION: glam::Vec3 = glam::Vec3::new(-0.57735026, -0.57735026, -0.57735026);
+
+/// Options of the CPU ray tracer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    /// Apply ordered dithering when quantizing the traced colors
+    pub dither: bool,
+    /// Handle of the highlighted sphere
+    pub selected: Option<SphereHandle>,
+    /// Number of reflection bounces after the primary ray hit
+    pub max_depth: u32,
+    /// Exposure in stops, the traced radiance is scaled by `2^exposure` before quantization
+    pub exposure: f32,
+    /// Darkening of the image corners, `0.0` disables the vignette
+    pub vignette: f32,
+    /// Radial offset of the red and blue channels, as a fraction of the distance to the
+    /// image center, `0.0` disables the effect
+    pub chromatic_aberration: f32,
+    /// Shadow rays traced toward each spherical light at every shaded point, averaged into
+    /// a soft shadow, point lights trace a single ray
+    pub shadow_samples: u32,
+    /// Number of rays traced per pixel
+    pub samples_per_pixel: u32,
+    /// Placement of the rays inside each pixel
+    pub aa_pattern: AaPattern,
+    /// Trace more samples on the noisy pixels, `None` traces a fixed number of samples
+    pub adaptive: Option<AdaptiveSampling>,
+    /// Closest hit distance along a ray, nearer geometry is ignored
+    pub t_min: f32,
+    /// Farthest hit distance along a ray, farther geometry shows the background
+    pub t_max: f32,
+    /// Color the hits fade to with the distance
+    pub fog_color: glam::Vec3,
+    /// Exponential fog density per unit of distance, `0.0` disables the fog
+    pub fog_density: f32,
+    /// Shutter open interval (seconds), each primary ray is cast at a random instant of
+    /// it to blur the moving spheres, `0.0` disables the motion blur
+    pub shutter_time: f32,
+    /// Color each pixel by the number of rays it cast, from blue (none) to red (the most
+    /// a pixel can cast without transparency), instead of the traced color
+    pub heatmap: bool,
+    /// Trace the image rows from the top down instead of from the bottom up
+    ///
+    /// Row 0 is the bottom of the view by default, matching the display quad which samples
+    /// row 0 at its bottom edge (`QUAD_VERTICES` maps `v = 0` to the bottom vertices). Image
+    /// files and top-left based consumers expect row 0 at the top.
+    pub flip_y: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            dither: true,
+            selected: None,
+            max_depth: 2,
+            exposure: 0.0,
+            vignette: 0.0,
+            chromatic_aberration: 0.0,
+            shadow_samples: 8,
+            samples_per_pixel: 1,
+            aa_pattern: AaPattern::Grid,
+            adaptive: None,
+            t_min: 0.0,
+            t_max: f32::MAX,
+            fog_color: glam::Vec3::splat(0.5),
+            fog_density: 0.0,
+            shutter_time: 0.0,
+            heatmap: false,
+            flip_y: false,
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Row of the view, counted from the bottom, traced into the image row `y`.
+    pub fn view_row(&self, y: u32, height: u32) -> u32 {
+        if self.flip_y {
+            height - 1 - y
+        } else {
+            y
+        }
+    }
+}
+
+/// Distribution of the supersampling rays inside a pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AaPattern {
+    /// Regular lattice, the sample count is rounded down to a square number
+    Grid,
+    /// Halton sequence in bases 2 and 3
+    Halton,
+}
+
+impl AaPattern {
+    /// Positions of the samples inside the pixel, in the `[0, 1)` range.
+    pub fn offsets(&self, count: u32) -> Vec<glam::Vec2> {
+        match self {
+            Self::Grid => {
+                let side = ((count as f32).sqrt() as u32).max(1);
+                let step = 1.0 / side as f32;
+                (0..side * side)
+                    .map(|i| {
+                        glam::Vec2::new((i % side) as f32 + 0.5, (i / side) as f32 + 0.5) * step
+                    })
+                    .collect()
+            }
+            Self::Halton => (1..=count.max(1))
+                .map(|i| glam::Vec2::new(halton(i, 2), halton(i, 3)))
+                .collect(),
+        }
+    }
+}
+
+/// Fewest initial samples of a pixel under adaptive sampling
+const MIN_ADAPTIVE_SAMPLES: usize = 2;
+
+/// Refinement of the pixels whose initial samples disagree
+///
+/// The initial samples come from `samples_per_pixel` and the AA pattern. When they are
+/// fewer than two, two Halton samples are traced instead so the variance can be estimated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Total number of samples of a refined pixel, including the initial samples
+    pub max_samples: u32,
+    /// Luminance variance of the initial samples above which the pixel is refined
+    pub variance_threshold: f32,
+}
+
+/// Running sum of the samples of a pixel
+#[derive(Debug, Default)]
+struct PixelSamples {
+    sum: glam::Vec4,
+    luminance_sum: f32,
+    luminance_squared_sum: f32,
+    count: u32,
+}
+
+impl PixelSamples {
+    fn add(&mut self, color: glam::Vec4) {
+        let luminance = color
+            .truncate()
+            .dot(glam::Vec3::new(0.2126, 0.7152, 0.0722));
+        self.sum += color;
+        self.luminance_sum += luminance;
+        self.luminance_squared_sum += luminance * luminance;
+        self.count += 1;
+    }
+
+    fn mean(&self) -> glam::Vec4 {
+        self.sum / self.count.max(1) as f32
+    }
+
+    /// Luminance variance of the samples.
+    fn variance(&self) -> f32 {
+        let count = self.count.max(1) as f32;
+        let mean = self.luminance_sum / count;
+        (self.luminance_squared_sum / count - mean * mean).max(0.0)
+    }
+}
+
+/// Counters accumulated while tracing an image
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Rays leaving the camera, one per pixel sample
+    pub primary_rays: u64,
+    /// Every traced ray, including reflected and transmitted rays
+    pub rays_cast: u64,
+    /// Rays that hit a sphere
+    pub hits: u64,
+    /// Rays traced from the hits toward the lights, not included in `rays_cast`
+    pub shadow_rays: u64,
+}
+
+impl std::ops::AddAssign for RenderStats {
+    fn add_assign(&mut self, other: Self) {
+        self.primary_rays += other.primary_rays;
+        self.rays_cast += other.rays_cast;
+        self.hits += other.hits;
+        self.shadow_rays += other.shadow_rays;
+    }
+}
+
+impl std::iter::Sum for RenderStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |mut total, stats| {
+            total += stats;
+            total
+        })
+    }
+}
+
+/// Trace the scene into the image and upload it to the texture.
+pub fn render_to_texture(
+    img: &mut RgbaImage,
+    texture: &Texture,
+    scene: &Scene,
+    camera: &Camera,
+    queue: &wgpu::Queue,
+    settings: &RenderSettings,
+) -> RenderStats {
+    let stats = render_image(img, scene, camera, settings);
+    texture.update_data(queue, img, img.width(), img.height());
+    stats
+}
+
+/// Trace the scene from the camera into every pixel of the image.
+pub fn render_image(
+    img: &mut RgbaImage,
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+) -> RenderStats {
+    render_image_with_sample_counts(img, scene, camera, settings, None)
+}
+
+/// Trace the scene into the image like [`render_image`], storing the number of samples
+/// traced for each pixel, row by row, in `sample_counts` when given.
+///
+/// # Panics
+///
+/// If `sample_counts` does not have one entry per pixel.
+pub fn render_image_with_sample_counts(
+    img: &mut RgbaImage,
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+    sample_counts: Option<&mut [u32]>,
+) -> RenderStats {
+    let (width, height) = img.dimensions();
+    let _span =
+        tracing::debug_span!("render_image", width, height, pixels = width * height).entered();
+    let exposure = settings.exposure.exp2();
+    let rays = camera.ray_generator(width, height);
+    let mut offsets = settings.aa_pattern.offsets(settings.samples_per_pixel);
+    if settings.adaptive.is_some() && offsets.len() < MIN_ADAPTIVE_SAMPLES {
+        // The variance needs several samples, fewer would never refine any pixel.
+        offsets = AaPattern::Halton.offsets(MIN_ADAPTIVE_SAMPLES as u32);
+    }
+    // Additional samples of the noisy pixels, continuing the low discrepancy sequence.
+    let adaptive_offsets: Vec<_> = settings.adaptive.map_or(Vec::new(), |adaptive| {
+        AaPattern::Halton
+            .offsets(adaptive.max_samples)
+            .into_iter()
+            .skip(offsets.len())
+            .collect()
+    });
+
+    // Every sample of a pixel bouncing up to the maximum depth.
+    let heatmap_rays =
+        ((offsets.len() + adaptive_offsets.len()) as u32 * (settings.max_depth + 1)).max(1) as f32;
+
+    let render_row = |y: u32, row: &mut [u8], mut counts: Option<&mut [u32]>| {
+        let mut stats = RenderStats::default();
+        let view_y = settings.view_row(y, height);
+        for (x, pixel) in (0..width).zip(row.chunks_exact_mut(4)) {
+            let pixel_rays = stats.rays_cast;
+            // Seeded by the pixel so the result does not depend on the traversal order.
+            let mut rng = Rng::new((view_y as u64) << 32 | x as u64);
+            let mut trace_samples = |offsets: &[glam::Vec2], samples: &mut PixelSamples| {
+                for offset in offsets {
+                    let mut ray = rays.ray(x, view_y, *offset);
+                    if settings.shutter_time > 0.0 {
+                        ray = ray.with_time(rng.range(0.0, settings.shutter_time));
+                    }
+                    samples.add(cast_ray_with_stats(scene, &ray, settings, &mut stats));
+                }
+            };
+
+            let mut samples = PixelSamples::default();
+            trace_samples(&offsets, &mut samples);
+            if let Some(adaptive) = settings.adaptive {
+                if samples.variance() > adaptive.variance_threshold {
+                    trace_samples(&adaptive_offsets, &mut samples);
+                }
+            }
+            if let Some(counts) = counts.as_deref_mut() {
+                counts[x as usize] = samples.count;
+            }
+            if settings.heatmap {
+                let rays = (stats.rays_cast - pixel_rays) as f32;
+                let color = heat_color(rays / heatmap_rays).extend(1.0);
+                pixel.copy_from_slice(&convert_rgba(color, 0.0));
+                continue;
+            }
+
+            let color = samples.mean();
+            let color = color * glam::Vec4::new(exposure, exposure, exposure, 1.0);
+            let threshold = if settings.dither {
+                dither_threshold(x, y)
+            } else {
+                0.0
+            };
+            let color = sanitize_color(color).clamp(glam::Vec4::ZERO, glam::Vec4::ONE);
+            let falloff = vignette(x, y, width, height, settings.vignette);
+            let color = color * glam::Vec4::new(falloff, falloff, falloff, 1.0);
+            pixel.copy_from_slice(&convert_rgba(color, threshold));
+        }
+        stats
+    };
+
+    // The rows are traced on the current rayon pool, every pixel is independent so the
+    // output does not depend on the number of threads.
+    let rows = img.par_chunks_mut(width as usize * 4).enumerate();
+    let stats = match sample_counts {
+        Some(counts) => {
+            assert_eq!(
+                counts.len(),
+                (width * height) as usize,
+                "sample counts size mismatch"
+            );
+            rows.zip(counts.par_chunks_mut(width as usize))
+                .map(|((y, pixels), counts)| render_row(y as u32, pixels, Some(counts)))
+                .sum()
+        }
+        None => rows
+            .map(|(y, pixels)| render_row(y as u32, pixels, None))
+            .sum(),
+    };
+
+    if settings.chromatic_aberration != 0.0 {
+        *img = chromatic_aberration(img, settings.chromatic_aberration);
+    }
+
+    stats
+}
+
+/// Worker threads tracing the images, owned by the caller so the threads are kept across
+/// traces and released with it.
+#[derive(Debug, Default)]
+pub struct TracerPool {
+    /// Dedicated pool, `None` traces on the global rayon pool
+    pool: Option<rayon::ThreadPool>,
+    thread_count: Option<usize>,
+}
+
+impl TracerPool {
+    /// Pool of `thread_count` threads, `None` uses the global rayon pool sized to the
+    /// available parallelism.
+    pub fn new(thread_count: Option<usize>) -> Self {
+        let mut pool = Self::default();
+        pool.set_thread_count(thread_count);
+        pool
+    }
+
+    pub fn thread_count(&self) -> Option<usize> {
+        self.thread_count
+    }
+
+    /// Change the number of threads, the pool is only rebuilt when the count changes.
+    pub fn set_thread_count(&mut self, thread_count: Option<usize>) {
+        let thread_count = thread_count.map(|threads| threads.max(1));
+        if thread_count == self.thread_count {
+            return;
+        }
+
+        self.thread_count = thread_count;
+        self.pool = thread_count.map(|threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(|index| format!("ray tracer {}", index))
+                .build()
+                .expect("failed to spawn the ray tracing threads")
+        });
+    }
+
+    /// Run the operation with its parallel work, e.g. [`render_image`], on the pool threads.
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+}
+
+/// Resolution scale of the traces while the camera moves
+pub const INTERACTIVE_RENDER_SCALE: f32 = 0.5;
+/// Updates without camera movement before a reduced resolution trace is refined
+pub const IDLE_FRAMES_BEFORE_FULL_RES: u32 = 10;
+
+/// Resolution of the traces following the camera, reduced while it moves to keep the
+/// interaction responsive and restored once it stays still.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InteractiveResolution {
+    /// Updates since the camera last moved
+    idle_frames: u32,
+    /// The last trace was at a reduced resolution
+    reduced: bool,
+}
+
+impl InteractiveResolution {
+    /// Record an update, returning the scale to trace the image at, `None` when the last
+    /// trace is still current.
+    ///
+    /// `stale` is set when the scene, camera or settings changed since the last trace.
+    pub fn update(&mut self, camera_moved: bool, stale: bool) -> Option<f32> {
+        if camera_moved {
+            self.idle_frames = 0;
+        } else {
+            self.idle_frames = self.idle_frames.saturating_add(1);
+        }
+
+        let scale = if stale && camera_moved {
+            INTERACTIVE_RENDER_SCALE
+        } else if stale || (self.reduced && self.idle_frames >= IDLE_FRAMES_BEFORE_FULL_RES) {
+            1.0
+        } else {
+            return None;
+        };
+        self.reduced = scale < 1.0;
+        Some(scale)
+    }
+
+    /// The last trace was at a reduced resolution.
+    pub fn reduced(&self) -> bool {
+        self.reduced
+    }
+}
+
+/// Map a value in the `[0, 1]` range through a blue, cyan, green, yellow, red gradient,
+/// values outside the range are clamped.
+pub fn heat_color(value: f32) -> glam::Vec3 {
+    const STOPS: [glam::Vec3; 5] = [
+        glam::Vec3::new(0.0, 0.0, 1.0),
+        glam::Vec3::new(0.0, 1.0, 1.0),
+        glam::Vec3::new(0.0, 1.0, 0.0),
+        glam::Vec3::new(1.0, 1.0, 0.0),
+        glam::Vec3::new(1.0, 0.0, 0.0),
+    ];
+
+    let position = value.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (position as usize).min(STOPS.len() - 2);
+    STOPS[index].lerp(STOPS[index + 1], position - index as f32)
+}
+
+/// Resample the image reading the red channel further from the center and the blue channel
+/// closer to it, by `amount` times the pixel distance to the center.
+pub fn chromatic_aberration(img: &RgbaImage, amount: f32) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let center = glam::Vec2::new(width as f32, height as f32) * 0.5;
+    let sample = |position: glam::Vec2| {
+        let x = (position.x.max(0.0) as u32).min(width - 1);
+        let y = (position.y.max(0.0) as u32).min(height - 1);
+        img.get_pixel(x, y).0
+    };
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let position = glam::Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+        let offset = (position - center) * amount;
+        let [_, g, _, a] = img.get_pixel(x, y).0;
+        let r = sample(position + offset)[0];
+        let b = sample(position - offset)[2];
+        Rgba([r, g, b, a])
+    })
+}
+
+/// Brightness factor of the pixel, decreasing with the squared distance from the image
+/// center down to `1 - strength` at the corners.
+pub fn vignette(x: u32, y: u32, width: u32, height: u32, strength: f32) -> f32 {
+    if strength == 0.0 {
+        return 1.0;
+    }
+
+    let center = glam::Vec2::new(width as f32, height as f32) * 0.5;
+    let offset = (glam::Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - center) / center;
+    // Normalized so the corners are at distance 1.
+    let distance_squared = offset.length_squared() * 0.5;
+    (1.0 - strength * distance_squared).clamp(0.0, 1.0)
+}
+
+/// 4x4 Bayer matrix used for ordered dithering
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Deterministic per pixel quantization threshold in the `[0, 1)` range.
+pub fn dither_threshold(x: u32, y: u32) -> f32 {
+    (BAYER_4X4[(y % 4) as usize][(x % 4) as usize] as f32 + 0.5) / 16.0
+}
+
+/// Quantize the color to 8 bits, `threshold` is the sub-LSB offset added to the color
+/// channels before truncation (`0.0` disables dithering).
+pub fn convert_rgba(color: glam::Vec4, threshold: f32) -> [u8; 4] {
+    let r = (color.x * 255.0 + threshold) as u8;
+    let g = (color.y * 255.0 + threshold) as u8;
+    let b = (color.z * 255.0 + threshold) as u8;
+    let a = (color.w * 255.0) as u8;
+    [r, g, b, a]
+}
+
+/// Trace a ray through the scene, returning the RGBA color it carries.
+pub fn cast_ray(scene: &Scene, ray: &Ray, settings: &RenderSettings) -> glam::Vec4 {
+    cast_ray_with_stats(scene, ray, settings, &mut RenderStats::default())
+}
+
+/// Trace a ray through the scene like [`cast_ray`], adding the traced rays to the stats.
+pub fn cast_ray_with_stats(
+    scene: &Scene,
+    ray: &Ray,
+    settings: &RenderSettings,
+    stats: &mut RenderStats,
+) -> glam::Vec4 {
+    stats.primary_rays += 1;
+    let depth = settings.max_depth.min(MAX_BOUNCE_DEPTH);
+    // Seeded by the ray, each supersample picks different points of the area lights.
+    let mut rng =
+        Rng::new((ray.direction.x.to_bits() as u64) << 32 | ray.direction.y.to_bits() as u64);
+    let color = trace(scene, ray, settings, depth, true, &mut rng, stats);
+    glam::Vec4::new(color.x, color.y, color.z, 1.0)
+}
+
+/// Radiance carried by the ray, following up to `depth` reflection bounces.
+///
+/// `primary` is set for the rays leaving the camera, including the ones continuing through
+/// transparent spheres.
+fn trace(
+    scene: &Scene,
+    ray: &Ray,
+    settings: &RenderSettings,
+    depth: u32,
+    primary: bool,
+    rng: &mut Rng,
+    stats: &mut RenderStats,
+) -> glam::Vec3 {
+    stats.rays_cast += 1;
+    let Some((index, hit)) = scene.intersect_closest(ray, settings.t_min, settings.t_max) else {
+        return scene.background().sample(ray.direction);
+    };
+    stats.hits += 1;
+    let sphere = &scene.spheres()[index];
+
+    let selected = primary && settings.selected == scene.handle(index);
+
+    // Silhouette of the selected sphere, where the normal is near perpendicular to the ray.
+    if selected && hit.normal.dot(-ray.direction) < SELECTION_OUTLINE {
+        return SELECTION_COLOR.truncate();
+    }
+
+    let mut color = sphere.albedo * scene.ambient();
+    if scene.lights().is_empty() {
+        let intensity = hit.normal.dot(-DEFAULT_LIGHT_DIRECTION).max(0.0); // == cos(angle)
+        color += sphere.albedo * intensity;
+    }
+    for light in scene.lights() {
+        let intensity = hit.normal.dot((light.position - hit.position).normalize());
+        if intensity > 0.0 {
+            let visibility = light_visibility(scene, &hit, light, ray.time, settings, rng, stats);
+            color += sphere.albedo * light.color * intensity * visibility;
+        }
+    }
+
+    if depth > 0 {
+        let reflected = ray.direction - 2.0 * ray.direction.dot(hit.normal) * hit.normal;
+        let reflected =
+            Ray::new(hit.position + hit.normal * HIT_BIAS, reflected).with_time(ray.time);
+        let reflection = trace(scene, &reflected, settings, depth - 1, false, rng, stats);
+        let tint = if sphere.metallic {
+            sphere.albedo
+        } else {
+            glam::Vec3::ONE
+        };
+        color += reflection * tint * REFLECTION_FACTOR;
+    }
+
+    if selected {
+        color = color.lerp(SELECTION_COLOR.truncate(), SELECTION_TINT);
+    }
+
+    // Plain alpha blending, the ray continues in the same direction past the exit point of
+    // the sphere, so its far side is not blended a second time.
+    if sphere.alpha < 1.0 {
+        let exit = sphere
+            .intersect_interval(ray)
+            .map_or(hit.distance, |(_, exit)| exit.max(hit.distance));
+        let through =
+            Ray::new(ray.at(exit) + ray.direction * HIT_BIAS, ray.direction).with_time(ray.time);
+        let behind = trace(scene, &through, settings, depth, primary, rng, stats);
+        color = behind.lerp(color, sphere.alpha.max(0.0));
+    }
+
+    if settings.fog_density > 0.0 {
+        let fog = 1.0 - (-settings.fog_density * hit.distance).exp();
+        color = color.lerp(settings.fog_color, fog);
+    }
+
+    color
+}
+
+/// Fraction of the shadow rays from the hit reaching the light, between `0.0` in the umbra
+/// and `1.0` when fully lit, the occluders are placed at the instant `time`.
+fn light_visibility(
+    scene: &Scene,
+    hit: &Hit,
+    light: &PointLight,
+    time: f32,
+    settings: &RenderSettings,
+    rng: &mut Rng,
+    stats: &mut RenderStats,
+) -> f32 {
+    let origin = hit.position + hit.normal * HIT_BIAS;
+    let samples = if light.radius > 0.0 {
+        settings.shadow_samples.max(1)
+    } else {
+        1
+    };
+
+    let visible = (0..samples)
+        .filter(|_| {
+            let offset = light.sample_point(rng) - origin;
+            let shadow_ray = Ray::new(origin, offset).with_time(time);
+            stats.shadow_rays += 1;
+            scene
+                .intersect_closest(&shadow_ray, 0.0, offset.length())
+                .is_none()
+        })
+        .count();
+    visible as f32 / samples as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scene::Sphere, *};
+
+    /// Number of horizontally adjacent pixels with the same red value in a slow gradient
+    /// spanning a few quantization steps.
+    fn equal_neighbours(dither: bool) -> usize {
+        let (width, height) = (64, 4);
+        let mut equal = 0;
+        for y in 0..height {
+            let row: Vec<u8> = (0..width)
+                .map(|x| {
+                    let value = 0.5 + x as f32 / width as f32 * 4.0 / 255.0;
+                    let threshold = if dither { dither_threshold(x, y) } else { 0.0 };
+                    convert_rgba(glam::Vec4::new(value, value, value, 1.0), threshold)[0]
+                })
+                .collect();
+            equal += row.windows(2).filter(|pair| pair[0] == pair[1]).count();
+        }
+        equal
+    }
+
+    #[test]
+    fn dithering_breaks_up_the_gradient_bands() {
+        assert!(equal_neighbours(true) < equal_neighbours(false));
+    }
+
+    #[test]
+    fn selected_sphere_center_is_highlighted() {
+        let mut scene = Scene::default();
+        let handle = scene.add_sphere(Sphere::default());
+        let camera = Camera::default();
+        let ray = camera.ray_through_pixel(4, 4, 9, 9);
+
+        let mut settings = RenderSettings::default();
+        let unselected = cast_ray(&scene, &ray, &settings);
+        assert_ne!(unselected, glam::Vec4::new(0.0, 0.0, 0.0, 1.0));
+        settings.selected = Some(handle);
+        assert_ne!(cast_ray(&scene, &ray, &settings), unselected);
+    }
+
+    #[test]
+    fn reflections_need_a_bounce_depth() {
+        let mut scene = Scene::default();
+        scene.add_sphere(Sphere {
+            radius: 1.0,
+            ..Default::default()
+        });
+        scene.add_sphere(Sphere {
+            position: glam::Vec3::new(-5.0, 0.0, 0.0),
+            radius: 1.0,
+            ..Default::default()
+        });
+        // Hits the unlit side of the first sphere, which mirrors it toward the lit side of
+        // the second one.
+        let ray = Ray::new(
+            glam::Vec3::new(-std::f32::consts::FRAC_1_SQRT_2, 0.0, 5.0),
+            glam::Vec3::NEG_Z,
+        );
+
+        let direct = cast_ray(
+            &scene,
+            &ray,
+            &RenderSettings {
+                max_depth: 0,
+                ..Default::default()
+            },
+        );
+        let reflected = cast_ray(
+            &scene,
+            &ray,
+            &RenderSettings {
+                max_depth: 1,
+                ..Default::default()
+            },
+        );
+        assert!(direct.truncate().length() < 1e-3);
+        assert!(reflected.truncate().cmpgt(direct.truncate() + 0.1).all());
+    }
+
+    #[test]
+    fn center_of_the_default_view_hits_the_scene() {
+        let mut scene = Scene::default();
+        scene.add_sphere(Sphere::default());
+        let ray = Camera::default().ray_through_pixel(4, 4, 9, 9);
+
+        let color = cast_ray(&scene, &ray, &RenderSettings::default());
+        assert_ne!(color, glam::Vec4::new(0.0, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn ambient_lights_the_side_facing_away_from_the_light() {
+        let mut scene = Scene::default();
+        let albedo = glam::Vec3::new(0.8, 0.4, 0.2);
+        scene.add_sphere(Sphere {
+            albedo,
+            ..Default::default()
+        });
+        let ambient = glam::Vec3::splat(0.25);
+        scene.set_ambient(ambient);
+        // The light travels along -(1, 1, 1), so this ray hits the unlit side.
+        let ray = Ray::new(glam::Vec3::splat(-3.0), glam::Vec3::ONE);
+        let settings = RenderSettings {
+            max_depth: 0,
+            ..Default::default()
+        };
+
+        let color = cast_ray(&scene, &ray, &settings);
+        assert!((color.truncate() - albedo * ambient).length() < 1e-4);
+    }
+
+    /// Trace a small image with the dithering off.
+    fn render(scene: &Scene, settings: RenderSettings) -> RgbaImage {
+        let mut img = RgbaImage::new(16, 16);
+        let settings = RenderSettings {
+            dither: false,
+            ..settings
+        };
+        let mut camera = Camera::default();
+        camera.projection.aspect_ratio = 1.0;
+        render_image(&mut img, scene, &camera, &settings);
+        img
+    }
+
+    #[test]
+    fn exposure_scales_the_radiance_in_stops() {
+        let mut scene = Scene::default();
+        scene.set_background(background::Background::Color(glam::Vec3::splat(0.25)));
+
+        let img = render(&scene, RenderSettings::default());
+        assert_eq!(img.get_pixel(8, 8).0, [63, 63, 63, 255]);
+        let img = render(
+            &scene,
+            RenderSettings {
+                exposure: 1.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(img.get_pixel(8, 8).0, [127, 127, 127, 255]);
+    }
+
+    #[test]
+    fn half_transparent_sphere_blends_with_the_one_behind() {
+        let red = glam::Vec3::X;
+        let blue = glam::Vec3::Z;
+        let mut scene = Scene::default();
+        scene.add_sphere(Sphere {
+            albedo: red,
+            alpha: 0.5,
+            ..Default::default()
+        });
+        scene.add_sphere(Sphere {
+            position: glam::Vec3::new(0.0, 0.0, -3.0),
+            albedo: blue,
+            ..Default::default()
+        });
+        let ray = Ray::new(glam::Vec3::new(0.0, 0.0, 5.0), glam::Vec3::NEG_Z);
+        let settings = RenderSettings {
+            max_depth: 0,
+            ..Default::default()
+        };
+
+        // Both spheres are hit head-on, so they receive the same light.
+        let intensity = glam::Vec3::ONE.normalize().z;
+        let expected = (red + blue) * 0.5 * intensity;
+        let color = cast_ray(&scene, &ray, &settings);
+        assert!((color.truncate() - expected).length() < 1e-4);
+    }
+
+    #[test]
+    fn zero_vignette_leaves_the_image_unchanged() {
+        for (x, y) in [(0, 0), (8, 8), (15, 0), (15, 15)] {
+            assert_eq!(vignette(x, y, 16, 16, 0.0), 1.0);
+        }
+        assert!(vignette(0, 0, 16, 16, 0.5) < vignette(8, 8, 16, 16, 0.5));
+        assert!(vignette(0, 0, 16, 16, 0.5) >= 0.5);
+    }
+
+    #[test]
+    fn chromatic_aberration_moves_the_red_and_blue_edges_apart() {
+        // White left of the column 24, right of the image center.
+        let img = RgbaImage::from_fn(32, 4, |x, _| {
+            if x < 24 {
+                Rgba([255; 4])
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        });
+        let shifted = chromatic_aberration(&img, 0.25);
+        let edge = |channel: usize| (0..32).find(|&x| shifted.get_pixel(x, 2).0[channel] == 0);
+
+        assert_eq!(edge(1), Some(24));
+        assert!(edge(0).unwrap() < 24);
+        assert!(edge(2).unwrap() > 24);
+    }
+
+    #[test]
+    fn spherical_light_casts_a_penumbra() {
+        let mut scene = Scene::default();
+        scene.add_sphere(Sphere {
+            position: glam::Vec3::new(0.0, 2.0, 0.0),
+            ..Default::default()
+        });
+        let hit = Hit {
+            distance: 1.0,
+            position: glam::Vec3::ZERO,
+            normal: glam::Vec3::Y,
+            uv: glam::Vec2::ZERO,
+            front_face: true,
+        };
+        let settings = RenderSettings {
+            shadow_samples: 64,
+            ..Default::default()
+        };
+        let visibility = |light: &PointLight, stats: &mut RenderStats| {
+            light_visibility(&scene, &hit, light, 0.0, &settings, &mut Rng::new(7), stats)
+        };
+
+        let mut stats = RenderStats::default();
+        let point = PointLight {
+            position: glam::Vec3::new(0.0, 4.0, 0.0),
+            ..Default::default()
+        };
+        assert_eq!(visibility(&point, &mut stats), 0.0);
+        assert_eq!(stats.shadow_rays, 1);
+
+        let sphere = PointLight {
+            radius: 3.0,
+            ..point
+        };
+        let penumbra = visibility(&sphere, &mut stats);
+        assert!(penumbra > 0.0 && penumbra < 1.0, "{}", penumbra);
+        assert_eq!(stats.shadow_rays, 65);
+
+        let unoccluded = PointLight {
+            position: glam::Vec3::new(4.0, 1.0, 0.0),
+            ..point
+        };
+        assert_eq!(visibility(&unoccluded, &mut stats), 1.0);
+    }
+
+    #[test]
+    fn grid_offsets_are_centered_in_the_cells() {
+        // Rounded down to the largest square count.
+        let offsets = AaPattern::Grid.offsets(5);
+        assert_eq!(
+            offsets,
+            [
+                glam::Vec2::new(0.25, 0.25),
+                glam::Vec2::new(0.75, 0.25),
+                glam::Vec2::new(0.25, 0.75),
+                glam::Vec2::new(0.75, 0.75),
+            ]
+        );
+        assert_eq!(AaPattern::Grid.offsets(0), [glam::Vec2::splat(0.5)]);
+    }
+
+    #[test]
+    fn halton_offsets_stay_inside_the_pixel() {
+        let offsets = AaPattern::Halton.offsets(16);
+        assert_eq!(offsets.len(), 16);
+        assert_eq!(offsets[0], glam::Vec2::new(0.5, halton(1, 3)));
+        assert!(offsets
+            .iter()
+            .all(|offset| offset.cmpge(glam::Vec2::ZERO).all()
+                && offset.cmplt(glam::Vec2::ONE).all()));
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_image() {
+        let mut scene = Scene::default();
+        scene.add_sphere(Sphere {
+            radius: 2.0,
+            ..Default::default()
+        });
+        let settings = RenderSettings {
+            samples_per_pixel: 4,
+            ..Default::default()
+        };
+
+        let pool = TracerPool::new(Some(2));
+        let threaded = pool.install(|| render(&scene, settings));
+        assert_eq!(render(&scene, settings), threaded);
+    }
+
+    #[test]
+    fn tracer_pool_runs_on_its_own_threads() {
+        let mut pool = TracerPool::new(Some(2));
+        assert_eq!(pool.install(rayon::current_num_threads), 2);
+
+        pool.set_thread_count(Some(0));
+        assert_eq!(pool.thread_count(), Some(1));
+        assert_eq!(pool.install(rayon::current_num_threads), 1);
+
+        pool.set_thread_count(None);
+        assert_eq!(
+            pool.install(rayon::current_num_threads),
+            rayon::current_num_threads()
+        );
+    }
+
+    #[test]
+    fn spheres_beyond_t_max_show_the_background() {
+        let mut scene = Scene::default();
+        scene.set_background(background::Background::Color(glam::Vec3::splat(0.25)));
+        let empty = render(&scene, RenderSettings::default());
+        scene.add_sphere(Sphere {
+            radius: 2.0,
+            ..Default::default()
+        });
+        // The sphere surface is 8 units away from the default camera.
+        assert_ne!(render(&scene, RenderSettings::default()), empty);
+
+        let settings = RenderSettings {
+            t_max: 5.0,
+            ..Default::default()
+        };
+        assert_eq!(render(&scene, settings), empty);
+    }
+
+    #[test]
+    fn fog_blends_far_spheres_more_than_near_ones() {
+        // Fraction of the fogless color left at the center of a sphere `distance` units away.
+        let remaining = |distance: f32, fog_density: f32| {
+            let mut scene = Scene::default();
+            scene.add_sphere(Sphere {
+                position: glam::Vec3::new(0.0, 0.0, -distance - 1.0),
+                radius: 1.0,
+                ..Default::default()
+            });
+            let ray = Ray::new(glam::Vec3::ZERO, glam::Vec3::NEG_Z);
+            let settings = RenderSettings {
+                fog_color: glam::Vec3::ZERO,
+                fog_density,
+                ..Default::default()
+            };
+            let clear = cast_ray(&scene, &ray, &RenderSettings::default());
+            cast_ray(&scene, &ray, &settings).x / clear.x
+        };
+
+        let near = remaining(2.0, 0.1);
+        let far = remaining(20.0, 0.1);
+        assert!((near - (-0.2f32).exp()).abs() < 1e-4);
+        assert!(far < near);
+
+        assert_eq!(remaining(20.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn adaptive_sampling_traces_at_least_two_samples() {
+        let scene = Scene::default();
+        let mut img = RgbaImage::new(4, 4);
+        let settings = RenderSettings {
+            samples_per_pixel: 1,
+            adaptive: Some(AdaptiveSampling {
+                max_samples: 16,
+                variance_threshold: 0.0,
+            }),
+            ..Default::default()
+        };
+        // The background is uniform, no pixel is refined past the initial samples.
+        let stats = render_image(&mut img, &scene, &Camera::default(), &settings);
+        assert_eq!(stats.primary_rays, 2 * 16);
+    }
+
+    #[test]
+    fn adaptive_sampling_refines_the_sphere_edge() {
+        // Black sphere over a white background, the pixels only vary across its edge.
+        let mut scene = Scene::default();
+        scene.set_background(background::Background::Color(glam::Vec3::ONE));
+        scene.add_sphere(Sphere {
+            radius: 2.0,
+            albedo: glam::Vec3::ZERO,
+            ..Default::default()
+        });
+        let settings = RenderSettings {
+            samples_per_pixel: 4,
+            adaptive: Some(AdaptiveSampling {
+                max_samples: 16,
+                variance_threshold: 0.01,
+            }),
+            ..Default::default()
+        };
+        let (width, height) = (16, 16);
+        let mut img = RgbaImage::new(width, height);
+        let mut counts = vec![0; (width * height) as usize];
+        let stats = render_image_with_sample_counts(
+            &mut img,
+            &scene,
+            &Camera::default(),
+            &settings,
+            Some(&mut counts),
+        );
+        assert_eq!(stats.primary_rays, counts.iter().map(|&n| n as u64).sum());
+
+        // A pixel is on the edge when the sphere covers some of its initial samples but not all.
+        let camera = Camera::default();
+        let offsets = settings.aa_pattern.offsets(settings.samples_per_pixel);
+        let (mut edge, mut flat) = (Vec::new(), Vec::new());
+        for y in 0..height {
+            for x in 0..width {
+                let covered = offsets
+                    .iter()
+                    .filter(|offset| {
+                        let ray = camera.ray_through_subpixel(x, y, **offset, width, height);
+                        scene.pick(&ray).is_some()
+                    })
+                    .count();
+                let count = counts[(y * width + x) as usize];
+                match covered {
+                    0 => flat.push(count),
+                    4 => {}
+                    _ => edge.push(count),
+                }
+            }
+        }
+
+        assert!(!edge.is_empty() && !flat.is_empty());
+        assert!(flat.iter().all(|&count| count == 4));
+        assert!(edge.iter().all(|&count| count == 16));
+    }
+
+    #[test]
+    fn metallic_spheres_tint_their_reflections() {
+        // Reflected radiance of a red sphere under a white sky.
+        let reflection = |metallic: bool| {
+            let mut scene = Scene::default();
+            scene.set_background(background::Background::Color(glam::Vec3::ONE));
+            scene.add_sphere(Sphere {
+                radius: 1.0,
+                albedo: glam::Vec3::X,
+                metallic,
+                ..Default::default()
+            });
+            let ray = Ray::new(glam::Vec3::new(0.0, 0.0, 5.0), glam::Vec3::NEG_Z);
+            let color = |max_depth| {
+                let settings = RenderSettings {
+                    max_depth,
+                    ..Default::default()
+                };
+                cast_ray(&scene, &ray, &settings).truncate()
+            };
+            color(1) - color(0)
+        };
+
+        let expected = REFLECTION_FACTOR * glam::Vec3::X;
+        assert!(reflection(true).abs_diff_eq(expected, 1e-5));
+        let expected = glam::Vec3::splat(REFLECTION_FACTOR);
+        assert!(reflection(false).abs_diff_eq(expected, 1e-5));
+    }
+
+    #[test]
+    fn moving_sphere_is_smeared_across_the_shutter_interval() {
+        let mut scene = Scene::default();
+        scene.add_sphere(Sphere {
+            radius: 1.0,
+            velocity: glam::Vec3::new(3.0, 0.0, 0.0),
+            ..Default::default()
+        });
+        // Columns of the middle row covered by the sphere.
+        let covered = |shutter_time| {
+            let settings = RenderSettings {
+                samples_per_pixel: 16,
+                aa_pattern: AaPattern::Halton,
+                shutter_time,
+                ..Default::default()
+            };
+            let img = render(&scene, settings);
+            let background = img.get_pixel(0, 0);
+            (0..img.width())
+                .filter(|&x| img.get_pixel(x, 8) != background)
+                .collect::<Vec<_>>()
+        };
+
+        let still = covered(0.0);
+        let blurred = covered(1.0);
+        assert!(blurred.len() > still.len());
+        // The sphere starts at its position and moves toward +X.
+        assert_eq!(blurred[0], still[0]);
+        assert!(blurred.last() > still.last());
+    }
+
+    #[test]
+    fn regions_with_more_spheres_cast_more_rays() {
+        let mut scene = Scene::default();
+        for z in [-3.0, -6.0, -9.0] {
+            scene.add_sphere(Sphere {
+                position: glam::Vec3::new(-3.0, 0.0, z),
+                radius: 1.0,
+                alpha: 0.5,
+                ..Default::default()
+            });
+        }
+        let rays_cast = |x: f32| {
+            let ray = Ray::new(glam::Vec3::new(x, 0.0, 0.0), glam::Vec3::NEG_Z);
+            let mut stats = RenderStats::default();
+            cast_ray_with_stats(&scene, &ray, &RenderSettings::default(), &mut stats);
+            stats.rays_cast
+        };
+
+        assert_eq!(rays_cast(3.0), 1);
+        assert!(rays_cast(-3.0) > rays_cast(3.0));
+    }
+
+    #[test]
+    fn heat_gradient_goes_from_blue_to_red() {
+        assert_eq!(heat_color(0.0), glam::Vec3::Z);
+        assert_eq!(heat_color(0.5), glam::Vec3::Y);
+        assert_eq!(heat_color(1.0), glam::Vec3::X);
+        assert_eq!(heat_color(2.0), glam::Vec3::X);
+    }
+
+    #[test]
+    fn flip_y_reverses_the_rows() {
+        // A single sphere above the view center makes the image vertically asymmetric.
+        let mut scene = Scene::default();
+        scene.add_sphere(scene::Sphere {
+            position: glam::Vec3::new(0.0, 2.0, 0.0),
+            radius: 2.0,
+            ..Default::default()
+        });
+
+        let img = render(&scene, RenderSettings::default());
+        let flipped = render(
+            &scene,
+            RenderSettings {
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+        assert_ne!(img, flipped);
+        assert_eq!(image::imageops::flip_vertical(&img), flipped);
+    }
+
+    #[test]
+    fn camera_motion_drops_to_half_resolution_until_idle() {
+        let mut resolution = InteractiveResolution::default();
+        assert_eq!(resolution.update(false, true), Some(1.0));
+        assert_eq!(resolution.update(false, false), None);
+
+        // Moving camera, every update traces again at the reduced resolution.
+        for _ in 0..3 {
+            assert_eq!(
+                resolution.update(true, true),
+                Some(INTERACTIVE_RENDER_SCALE)
+            );
+            assert!(resolution.reduced());
+        }
+
+        for _ in 1..IDLE_FRAMES_BEFORE_FULL_RES {
+            assert_eq!(resolution.update(false, false), None);
+        }
+        assert_eq!(resolution.update(false, false), Some(1.0));
+        assert!(!resolution.reduced());
+        assert_eq!(resolution.update(false, false), None);
+    }
+}
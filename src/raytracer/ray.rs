@@ -0,0 +1,59 @@
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: glam::Vec3,
+    /// Unit direction, guaranteed when built with [`Ray::new`]
+    pub direction: glam::Vec3,
+    /// Instant within the shutter interval (seconds), the spheres move by their velocity
+    pub time: f32,
+}
+
+impl Ray {
+    /// Create a ray, normalizing its direction.
+    pub fn new(origin: glam::Vec3, direction: glam::Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+            time: 0.0,
+        }
+    }
+
+    /// Same ray cast at another instant of the shutter interval.
+    pub fn with_time(self, time: f32) -> Self {
+        Self { time, ..self }
+    }
+
+    /// Point at distance `t` along the ray.
+    pub fn at(&self, t: f32) -> glam::Vec3 {
+        self.origin + self.direction * t
+    }
+}
+
+/// Ray intersection with a scene object.
+pub struct Hit {
+    pub distance: f32,
+    /// Hit point in world space
+    pub position: glam::Vec3,
+    /// Unit surface normal at the hit point, facing against the ray
+    pub normal: glam::Vec3,
+    /// The ray hit the outer side of the surface, the normal is flipped inward otherwise
+    pub front_face: bool,
+    /// Texture coordinates at the hit point, both in the `[0, 1]` range
+    pub uv: glam::Vec2,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_normalizes_the_direction() {
+        let ray = Ray::new(
+            glam::Vec3::new(1.0, 0.0, 0.0),
+            glam::Vec3::new(0.0, 3.0, 4.0),
+        );
+        assert!((ray.direction.length() - 1.0).abs() < 1e-6);
+        assert!(ray
+            .at(2.0)
+            .abs_diff_eq(glam::Vec3::new(1.0, 1.2, 1.6), 1e-6));
+    }
+}
@@ -0,0 +1,709 @@
+use std::fmt;
+
+use crate::util::rng::Rng;
+
+use super::{
+    background::Background,
+    light::PointLight,
+    ray::{Hit, Ray},
+};
+
+/// Radius range of the randomly generated spheres
+const RANDOM_SPHERE_RADIUS: (f32, f32) = (0.1, 0.5);
+/// Attempts to place a random sphere without overlapping the others
+const RANDOM_SPHERE_ATTEMPTS: u32 = 8;
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: glam::Vec3,
+    pub max: glam::Vec3,
+}
+
+/// Stable reference to a sphere, remains valid while other spheres are added or removed.
+///
+/// Handles are never reused, a handle to a removed sphere resolves to `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SphereHandle(u64);
+
+#[derive(Debug, Default)]
+pub struct Scene {
+    spheres: Vec<Sphere>,
+    /// Handle of each sphere, in the same order as `spheres`
+    handles: Vec<SphereHandle>,
+    next_handle: u64,
+    lights: Vec<PointLight>,
+    /// Fill light added to every shaded surface, scaled by its albedo
+    ambient: glam::Vec3,
+    background: Background,
+    /// Incremented on every mutation, allows caching data derived from the scene
+    version: u64,
+}
+
+impl Scene {
+    /// Generate a reproducible scene of `count` spheres scattered inside the bounds.
+    ///
+    /// Overlapping spheres are avoided by retrying a few positions, dense scenes may still
+    /// contain overlaps.
+    pub fn random_spheres(count: usize, seed: u64, bounds: Aabb) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut scene = Self::default();
+
+        for _ in 0..count {
+            let radius = rng.range(RANDOM_SPHERE_RADIUS.0, RANDOM_SPHERE_RADIUS.1);
+            let min = (bounds.min + radius).min(bounds.max);
+            let max = (bounds.max - radius).max(min);
+            let albedo = rng.vec3_range(glam::Vec3::ZERO, glam::Vec3::ONE);
+
+            let mut position = rng.vec3_range(min, max);
+            for _ in 1..RANDOM_SPHERE_ATTEMPTS {
+                let overlaps = scene
+                    .spheres
+                    .iter()
+                    .any(|sphere| sphere.position.distance(position) < sphere.radius + radius);
+                if !overlaps {
+                    break;
+                }
+                position = rng.vec3_range(min, max);
+            }
+
+            scene.add_sphere(Sphere {
+                position,
+                radius,
+                albedo,
+                ..Default::default()
+            });
+        }
+
+        scene
+    }
+
+    pub fn add_sphere(&mut self, sphere: Sphere) -> SphereHandle {
+        let handle = SphereHandle(self.next_handle);
+        self.next_handle += 1;
+        self.spheres.push(sphere);
+        self.handles.push(handle);
+        self.version += 1;
+        handle
+    }
+
+    /// Add the spheres in iteration order.
+    pub fn extend_spheres(&mut self, spheres: impl IntoIterator<Item = Sphere>) {
+        for sphere in spheres {
+            self.add_sphere(sphere);
+        }
+    }
+
+    /// Append the spheres of `other` after the current ones, keeping their order.
+    ///
+    /// The merged spheres get new handles and the lights of `other` are appended, its
+    /// ambient light and background are discarded.
+    pub fn merge(&mut self, other: Scene) {
+        self.extend_spheres(other.spheres);
+        for light in other.lights {
+            self.add_light(light);
+        }
+    }
+
+    /// Remove the sphere at the index, shifting the index of the following spheres.
+    ///
+    /// # Panics
+    ///
+    /// When the index is out of bounds.
+    pub fn remove_sphere(&mut self, index: usize) -> Sphere {
+        self.handles.remove(index);
+        self.version += 1;
+        self.spheres.remove(index)
+    }
+
+    /// Mutable access to the sphere at the index, the scene is considered modified.
+    pub fn sphere_mut(&mut self, index: usize) -> Option<&mut Sphere> {
+        self.version += 1;
+        self.spheres.get_mut(index)
+    }
+
+    /// Handle of the sphere at the index.
+    pub fn handle(&self, index: usize) -> Option<SphereHandle> {
+        self.handles.get(index).copied()
+    }
+
+    /// Current index of the sphere, `None` if it was removed.
+    pub fn index_of(&self, handle: SphereHandle) -> Option<usize> {
+        self.handles.iter().position(|h| *h == handle)
+    }
+
+    pub fn get(&self, handle: SphereHandle) -> Option<&Sphere> {
+        self.index_of(handle).map(|index| &self.spheres[index])
+    }
+
+    pub fn spheres(&self) -> &[Sphere] {
+        &self.spheres
+    }
+
+    pub fn add_light(&mut self, light: PointLight) {
+        self.lights.push(light);
+        self.version += 1;
+    }
+
+    /// Lights of the scene, scenes without lights are lit by a fixed directional light.
+    pub fn lights(&self) -> &[PointLight] {
+        &self.lights
+    }
+
+    pub fn ambient(&self) -> glam::Vec3 {
+        self.ambient
+    }
+
+    pub fn set_ambient(&mut self, ambient: glam::Vec3) {
+        self.ambient = ambient;
+        self.version += 1;
+    }
+
+    pub fn background(&self) -> &Background {
+        &self.background
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+        self.version += 1;
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Count the scene primitives by kind.
+    pub fn stats(&self) -> SceneStats {
+        let count = |filter: fn(&&Sphere) -> bool| self.spheres.iter().filter(filter).count();
+        SceneStats {
+            spheres: self.spheres.len(),
+            transparent: count(|sphere| sphere.alpha < 1.0),
+            metallic: count(|sphere| sphere.metallic),
+            moving: count(|sphere| sphere.velocity != glam::Vec3::ZERO),
+            lights: self.lights.len(),
+        }
+    }
+
+    /// Center and radius of a sphere enclosing every sphere of the scene, `None` when the
+    /// scene is empty.
+    pub fn bounding_sphere(&self) -> Option<(glam::Vec3, f32)> {
+        let (min, max) = self.spheres.iter().fold(
+            (
+                glam::Vec3::splat(f32::INFINITY),
+                glam::Vec3::splat(f32::NEG_INFINITY),
+            ),
+            |(min, max), sphere| {
+                (
+                    min.min(sphere.position - sphere.radius),
+                    max.max(sphere.position + sphere.radius),
+                )
+            },
+        );
+        let center = (min + max) * 0.5;
+        let radius = self
+            .spheres
+            .iter()
+            .map(|sphere| center.distance(sphere.position) + sphere.radius)
+            .reduce(f32::max)?;
+        Some((center, radius))
+    }
+
+    /// Find the closest sphere hit by the ray within the `[t_min, t_max]` distance range,
+    /// returning its index and the hit information.
+    pub fn intersect_closest(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(usize, Hit)> {
+        let (index, distance) = self
+            .spheres
+            .iter()
+            .enumerate()
+            .filter_map(|(index, sphere)| {
+                // The exit is hit when the entry is out of range, from inside the sphere.
+                let (entry, exit) = sphere.intersect_interval(ray)?;
+                [entry, exit]
+                    .into_iter()
+                    .find(|t| (t_min..=t_max).contains(t))
+                    .map(|t| (index, t))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+        Some((index, self.spheres[index].hit(ray, distance)))
+    }
+
+    /// Index of the sphere hit by the ray, `None` if the ray hits nothing.
+    pub fn pick(&self, ray: &Ray) -> Option<usize> {
+        self.intersect_closest(ray, 0.0, f32::MAX)
+            .map(|(index, _)| index)
+    }
+
+    /// Build a scene from the line based text format, one entry per line:
+    ///
+    /// ```text
+    /// # comment
+    /// sphere x y z radius r g b
+    /// light x y z r g b radius
+    /// ambient r g b
+    /// background r g b
+    /// ```
+    ///
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn parse(source: &str) -> Result<Scene, ParseError> {
+        let mut scene = Scene::default();
+        for (index, line) in source.lines().enumerate() {
+            let error = |kind| ParseError {
+                line: index + 1,
+                kind,
+            };
+            let mut words = line.split_whitespace();
+            let Some(keyword) = words.next().filter(|word| !word.starts_with('#')) else {
+                continue;
+            };
+            let expected = match keyword {
+                "sphere" | "light" => 7,
+                "ambient" | "background" => 3,
+                _ => return Err(error(ParseErrorKind::UnknownKeyword(keyword.to_string()))),
+            };
+            let values = words
+                .map(|word| {
+                    word.parse::<f32>()
+                        .map_err(|_| error(ParseErrorKind::InvalidNumber(word.to_string())))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if values.len() != expected {
+                return Err(error(ParseErrorKind::ArgumentCount {
+                    keyword: keyword.to_string(),
+                    expected,
+                    found: values.len(),
+                }));
+            }
+            let vec3 = |offset: usize| glam::Vec3::from_slice(&values[offset..offset + 3]);
+
+            match keyword {
+                "sphere" => {
+                    scene.add_sphere(Sphere {
+                        position: vec3(0),
+                        radius: values[3],
+                        albedo: vec3(4),
+                        ..Default::default()
+                    });
+                }
+                "light" => scene.add_light(PointLight {
+                    position: vec3(0),
+                    color: vec3(3),
+                    radius: values[6],
+                }),
+                "ambient" => scene.set_ambient(vec3(0)),
+                _ => scene.set_background(Background::Color(vec3(0))),
+            }
+        }
+        Ok(scene)
+    }
+}
+
+/// Failure to parse a scene, on the 1-based `line` of the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    UnknownKeyword(String),
+    /// The entry does not have the expected number of values
+    ArgumentCount {
+        keyword: String,
+        expected: usize,
+        found: usize,
+    },
+    InvalidNumber(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ParseErrorKind::UnknownKeyword(keyword) => write!(f, "unknown entry {:?}", keyword),
+            ParseErrorKind::ArgumentCount {
+                keyword,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} expects {} values, found {}",
+                keyword, expected, found
+            ),
+            ParseErrorKind::InvalidNumber(word) => write!(f, "invalid number {:?}", word),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Size of a scene, the transparent, metallic and moving counts are subsets of the spheres.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SceneStats {
+    pub spheres: usize,
+    pub transparent: usize,
+    pub metallic: usize,
+    pub moving: usize,
+    pub lights: usize,
+}
+
+impl fmt::Display for SceneStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} spheres ({} transparent, {} metallic, {} moving), {} lights",
+            self.spheres, self.transparent, self.metallic, self.moving, self.lights
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Sphere {
+    pub position: glam::Vec3,
+    pub radius: f32,
+
+    pub albedo: glam::Vec3,
+    /// Opacity of the surface, rays pass straight through the transparent fraction
+    /// without refraction
+    pub alpha: f32,
+    /// Metals tint their reflections with the albedo, dielectrics reflect uncolored light
+    pub metallic: bool,
+    /// Displacement per second of shutter time, moving spheres are blurred along it
+    pub velocity: glam::Vec3,
+}
+
+impl Sphere {
+    /// Center of the sphere at the instant `time` of the shutter interval.
+    pub fn position_at(&self, time: f32) -> glam::Vec3 {
+        self.position + self.velocity * time
+    }
+
+    /// Distance along the ray to the closest intersection in front of the ray origin.
+    ///
+    /// Rays starting inside the sphere hit its surface on the way out.
+    pub fn intersect(&self, ray: &Ray) -> Option<f32> {
+        let (entry, exit) = self.intersect_interval(ray)?;
+        [entry, exit].into_iter().find(|t| *t >= 0.0)
+    }
+
+    /// Distances along the line of the ray where it enters and exits the sphere, with
+    /// `entry <= exit`.
+    ///
+    /// The entry is negative when the ray origin is inside the sphere and both are negative
+    /// when the sphere is behind the ray, `None` when the line misses the sphere.
+    pub fn intersect_interval(&self, ray: &Ray) -> Option<(f32, f32)> {
+        // (bx^2 + by^2 + bz^2)t^2 + (2(axbx + ayby + azbz))t + (ax^2 + ay^2 + az^2 - r^2) = 0
+        // where
+        // a = ray origin
+        // b = ray direction
+        // r = radius
+        // t = hit distance
+        let origin = ray.origin - self.position_at(ray.time);
+
+        let a = glam::Vec3::dot(ray.direction, ray.direction);
+        // Half of the linear coefficient, the roots are (-h ± sqrt(h^2 - ac)) / a
+        let h = glam::Vec3::dot(origin, ray.direction);
+        let c = glam::Vec3::dot(origin, origin) - self.radius * self.radius;
+
+        // h^2 - ac computed from the distance between the center and the closest point of the
+        // line, h^2 and ac are nearly equal for distant spheres and cancel each other.
+        let closest = origin - ray.direction * (h / a);
+        let discriminant = a * (self.radius * self.radius - glam::Vec3::dot(closest, closest));
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        // Numerically stable roots, q adds two terms of the same sign so the subtraction
+        // in (-h ± root) cancelling the significant digits of the smaller root is avoided.
+        let q = -(h + h.signum() * discriminant.sqrt());
+        if q == 0.0 {
+            // Tangent line through the ray origin, h and c are both zero.
+            return Some((0.0, 0.0));
+        }
+        let (t0, t1) = (q / a, c / q);
+        Some((t0.min(t1), t0.max(t1)))
+    }
+
+    /// Hit information at `distance` along the ray.
+    pub fn hit(&self, ray: &Ray, distance: f32) -> Hit {
+        let position = ray.at(distance);
+        let outward_normal = (position - self.position_at(ray.time)).normalize();
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        Hit {
+            distance,
+            position,
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            front_face,
+            uv: sphere_uv(outward_normal),
+        }
+    }
+}
+
+impl Default for Sphere {
+    fn default() -> Self {
+        Self {
+            position: glam::Vec3::ZERO,
+            radius: 0.5,
+            albedo: glam::Vec3::ONE,
+            alpha: 1.0,
+            metallic: false,
+            velocity: glam::Vec3::ZERO,
+        }
+    }
+}
+
+/// Compute the spherical texture coordinates of a point on a sphere from its unit normal.
+///
+/// `u` wraps around the Y axis starting at the -X direction and `v` goes from `0` at
+/// the top pole (+Y) to `1` at the bottom pole (-Y).
+pub fn sphere_uv(normal: glam::Vec3) -> glam::Vec2 {
+    use std::f32::consts::PI;
+
+    // atan2 is well defined at the poles (returns 0) and ranges over [-PI, PI] at the seam.
+    let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * PI);
+    // clamp to guard asin against normals slightly out of the unit range.
+    let v = 0.5 - normal.y.clamp(-1.0, 1.0).asin() / PI;
+    glam::Vec2::new(u, v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sphere(position: glam::Vec3, radius: f32) -> Sphere {
+        Sphere {
+            position,
+            radius,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn uv_follows_the_spherical_coordinates() {
+        let uv = sphere_uv(glam::Vec3::X);
+        assert!((uv - glam::Vec2::new(0.5, 0.5)).length() < 1e-6);
+        assert!((sphere_uv(glam::Vec3::Z).x - 0.75).abs() < 1e-6);
+        assert_eq!(sphere_uv(glam::Vec3::Y).y, 0.0);
+        assert_eq!(sphere_uv(glam::Vec3::NEG_Y).y, 1.0);
+
+        for normal in [
+            glam::Vec3::NEG_X,
+            glam::Vec3::new(-1.0, 0.0, -1e-7),
+            glam::Vec3::new(1.0, 1.0, -1.0),
+            glam::Vec3::new(-1.0, -2.0, 3.0),
+        ] {
+            let uv = sphere_uv(normal.normalize());
+            assert!(uv.cmpge(glam::Vec2::ZERO).all() && uv.cmple(glam::Vec2::ONE).all());
+        }
+    }
+
+    #[test]
+    fn pick_returns_the_index_of_the_sphere_hit() {
+        let mut scene = Scene::default();
+        scene.add_sphere(sphere(glam::Vec3::new(-2.0, 0.0, 0.0), 0.5));
+        scene.add_sphere(sphere(glam::Vec3::new(2.0, 0.0, 0.0), 0.5));
+        let ray = |x: f32| Ray::new(glam::Vec3::new(x, 0.0, 5.0), glam::Vec3::NEG_Z);
+
+        assert_eq!(scene.pick(&ray(-2.0)), Some(0));
+        assert_eq!(scene.pick(&ray(2.0)), Some(1));
+        assert_eq!(scene.pick(&ray(0.0)), None);
+    }
+
+    #[test]
+    fn random_spheres_are_reproducible() {
+        let bounds = Aabb {
+            min: glam::Vec3::splat(-5.0),
+            max: glam::Vec3::splat(5.0),
+        };
+        let spheres = |seed| {
+            Scene::random_spheres(16, seed, bounds)
+                .spheres()
+                .iter()
+                .map(|sphere| (sphere.position, sphere.radius, sphere.albedo))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(spheres(42).len(), 16);
+        assert_eq!(spheres(42), spheres(42));
+        assert_ne!(spheres(42), spheres(43));
+    }
+
+    #[test]
+    fn handles_survive_the_removal_of_other_spheres() {
+        let mut scene = Scene::default();
+        let first = scene.add_sphere(sphere(glam::Vec3::X, 1.0));
+        let second = scene.add_sphere(sphere(glam::Vec3::Y, 1.0));
+
+        scene.remove_sphere(0);
+        assert_eq!(scene.index_of(second), Some(0));
+        assert_eq!(scene.get(second).unwrap().position, glam::Vec3::Y);
+        assert!(scene.get(first).is_none());
+
+        // Handles are not reused by the following spheres.
+        let third = scene.add_sphere(sphere(glam::Vec3::Z, 1.0));
+        assert_ne!(third, first);
+        assert!(scene.get(first).is_none());
+    }
+
+    #[test]
+    fn merge_appends_the_spheres_in_order() {
+        let mut scene = Scene::default();
+        scene.add_sphere(sphere(glam::Vec3::X, 1.0));
+        scene.set_ambient(glam::Vec3::splat(0.2));
+        let mut other = Scene::default();
+        other.extend_spheres([sphere(glam::Vec3::Y, 1.0), sphere(glam::Vec3::Z, 1.0)]);
+        other.set_ambient(glam::Vec3::ONE);
+        other.add_light(PointLight {
+            position: glam::Vec3::Y,
+            ..Default::default()
+        });
+
+        scene.merge(other);
+        assert_eq!(scene.lights().len(), 1);
+        let positions: Vec<_> = scene.spheres().iter().map(|s| s.position).collect();
+        assert_eq!(positions, [glam::Vec3::X, glam::Vec3::Y, glam::Vec3::Z]);
+        assert_eq!(scene.ambient(), glam::Vec3::splat(0.2));
+        // Every sphere keeps a distinct handle.
+        assert_ne!(scene.handle(0), scene.handle(1));
+        assert_eq!(scene.index_of(scene.handle(2).unwrap()), Some(2));
+    }
+
+    #[test]
+    fn interval_spans_the_sphere_along_the_ray() {
+        let sphere = sphere(glam::Vec3::new(0.0, 0.0, -5.0), 1.0);
+        let toward = Ray::new(glam::Vec3::ZERO, glam::Vec3::NEG_Z);
+        let away = Ray::new(glam::Vec3::ZERO, glam::Vec3::Z);
+        let miss = Ray::new(glam::Vec3::ZERO, glam::Vec3::X);
+
+        assert_eq!(sphere.intersect_interval(&toward), Some((4.0, 6.0)));
+        assert_eq!(sphere.intersect_interval(&away), Some((-6.0, -4.0)));
+        assert_eq!(sphere.intersect(&away), None);
+        assert_eq!(sphere.intersect_interval(&miss), None);
+    }
+
+    #[test]
+    fn ray_from_inside_hits_the_back_face() {
+        let mut scene = Scene::default();
+        scene.add_sphere(sphere(glam::Vec3::ZERO, 2.0));
+        let ray = Ray::new(glam::Vec3::ZERO, glam::Vec3::X);
+
+        let (index, hit) = scene.intersect_closest(&ray, 0.0, f32::MAX).unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(hit.distance, 2.0);
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, glam::Vec3::NEG_X);
+
+        let outside = Ray::new(glam::Vec3::new(-5.0, 0.0, 0.0), glam::Vec3::X);
+        let hit = scene.spheres()[0].hit(&outside, 3.0);
+        assert!(hit.front_face);
+        assert_eq!(hit.normal, glam::Vec3::NEG_X);
+    }
+
+    #[test]
+    fn stats_count_the_sphere_kinds() {
+        let mut scene = Scene::default();
+        scene.extend_spheres([
+            Sphere {
+                alpha: 0.5,
+                metallic: true,
+                ..Default::default()
+            },
+            Sphere {
+                velocity: glam::Vec3::X,
+                ..Default::default()
+            },
+            Sphere::default(),
+        ]);
+        scene.add_light(PointLight::default());
+
+        let stats = scene.stats();
+        assert_eq!(
+            stats,
+            SceneStats {
+                spheres: 3,
+                transparent: 1,
+                metallic: 1,
+                moving: 1,
+                lights: 1,
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "3 spheres (1 transparent, 1 metallic, 1 moving), 1 lights"
+        );
+    }
+
+    #[test]
+    fn parse_reads_every_entry() {
+        let scene = Scene::parse(
+            "# two spheres\n\
+             sphere 0 0 -5 1 1 0 0\n\
+             sphere 2 0 -5 0.5 0 1 0\n\
+             \n\
+             light 4 4 4 1 1 1 0.5\n\
+             ambient 0.1 0.1 0.1\n",
+        )
+        .unwrap();
+
+        assert_eq!(scene.spheres().len(), 2);
+        assert_eq!(scene.spheres()[1].position, glam::Vec3::new(2.0, 0.0, -5.0));
+        assert_eq!(scene.spheres()[1].radius, 0.5);
+        assert_eq!(scene.spheres()[1].albedo, glam::Vec3::Y);
+        assert_eq!(scene.lights().len(), 1);
+        assert_eq!(scene.lights()[0].position, glam::Vec3::splat(4.0));
+        assert_eq!(scene.lights()[0].radius, 0.5);
+        assert_eq!(scene.ambient(), glam::Vec3::splat(0.1));
+    }
+
+    #[test]
+    fn parse_errors_name_the_line() {
+        let error = Scene::parse("sphere 0 0 -5 1 1 0 0\nsphere 0 0 x 1 1 0 0\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.kind, ParseErrorKind::InvalidNumber("x".to_string()));
+        assert!(error.to_string().starts_with("line 2:"));
+
+        let error = Scene::parse("\nambient 1 1\n").unwrap_err();
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::ArgumentCount {
+                keyword: "ambient".to_string(),
+                expected: 3,
+                found: 2,
+            }
+        );
+
+        let error = Scene::parse("plane up 0").unwrap_err();
+        assert_eq!(error.line, 1);
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::UnknownKeyword("plane".to_string())
+        );
+    }
+
+    #[test]
+    fn distant_sphere_hit_matches_double_precision() {
+        let sphere = sphere(glam::Vec3::new(0.0, 0.0, -10_000.0), 1.0);
+        let ray = Ray::new(glam::Vec3::ZERO, glam::Vec3::new(5e-5, 0.0, -1.0));
+
+        // Entry root of the same quadratic in double precision.
+        let direction = ray.direction.as_dvec3();
+        let origin = -sphere.position.as_dvec3();
+        let a = direction.dot(direction);
+        let b = origin.dot(direction);
+        let c = origin.dot(origin) - 1.0;
+        let expected = (-b - (b * b - a * c).sqrt()) / a;
+
+        let distance = sphere.intersect(&ray).unwrap();
+        assert!(
+            (distance as f64 - expected).abs() < 1e-2,
+            "{distance} != {expected}"
+        );
+        let hit = sphere.hit(&ray, distance);
+        assert!((hit.position.distance(sphere.position) - 1.0).abs() < 1e-2);
+    }
+}
@@ -1,35 +1,69 @@
+use image::RgbaImage;
 use wgpu::util::DeviceExt;
 
-pub struct VertexBuffer(wgpu::Buffer);
+use crate::{
+    raytracer::{ray::Ray, scene::Aabb},
+    shader,
+};
+
+pub struct VertexBuffer {
+    buffer: wgpu::Buffer,
+    /// Size of a single vertex in bytes
+    stride: u64,
+}
 
 impl VertexBuffer {
-    pub fn init_immediate<'label>(
+    pub fn init_immediate<V: bytemuck::Pod>(
         device: &wgpu::Device,
-        content: &[u8],
-        label: Option<&'label str>,
+        content: &[V],
+        label: Option<&str>,
     ) -> Self {
         let init_descriptor = wgpu::util::BufferInitDescriptor {
             label,
-            contents: content,
+            contents: bytemuck::cast_slice(content),
             usage: wgpu::BufferUsages::VERTEX,
         };
         let buffer = device.create_buffer_init(&init_descriptor);
-        Self(buffer)
+        Self {
+            buffer,
+            stride: std::mem::size_of::<V>() as u64,
+        }
     }
 
-    pub fn init<'label>(device: &wgpu::Device, size: u64, label: Option<&'label str>) -> Self {
+    /// Create an uninitialized buffer holding `count` vertices of type `V`.
+    pub fn init<V: bytemuck::Pod>(device: &wgpu::Device, count: u32, label: Option<&str>) -> Self {
+        let stride = std::mem::size_of::<V>() as u64;
         let wgt_descriptor = wgpu::BufferDescriptor {
             label,
-            size,
+            size: stride * count as u64,
             usage: wgpu::BufferUsages::VERTEX,
             mapped_at_creation: false,
         };
         let buffer = device.create_buffer(&wgt_descriptor);
-        Self(buffer)
+        Self { buffer, stride }
     }
 
     pub fn buffer(&self) -> &wgpu::Buffer {
-        &self.0
+        &self.buffer
+    }
+
+    /// Number of vertices in the buffer.
+    pub fn len(&self) -> u32 {
+        (self.buffer.size() / self.stride) as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Size of a single vertex in bytes.
+    pub fn stride(&self) -> u64 {
+        self.stride
+    }
+
+    /// Size of the buffer in bytes.
+    pub fn byte_size(&self) -> u64 {
+        self.buffer.size()
     }
 }
 
@@ -54,27 +88,19 @@ macro_rules! index_buffer_init_immediate {
 }
 
 impl IndexBuffer {
-    pub fn init_immediate_u16<'label>(
-        device: &wgpu::Device,
-        content: &[u16],
-        label: Option<&'label str>,
-    ) -> Self {
+    pub fn init_immediate_u16(device: &wgpu::Device, content: &[u16], label: Option<&str>) -> Self {
         index_buffer_init_immediate!(device, content, label, Uint16)
     }
 
-    pub fn init_immediate_u32<'label>(
-        device: &wgpu::Device,
-        content: &[u32],
-        label: Option<&'label str>,
-    ) -> Self {
+    pub fn init_immediate_u32(device: &wgpu::Device, content: &[u32], label: Option<&str>) -> Self {
         index_buffer_init_immediate!(device, content, label, Uint32)
     }
 
-    pub fn init<'label>(
+    pub fn init(
         device: &wgpu::Device,
         count: u32,
         format: wgpu::IndexFormat,
-        label: Option<&'label str>,
+        label: Option<&str>,
     ) -> Self {
         let wgt_descriptor = wgpu::BufferDescriptor {
             label,
@@ -108,6 +134,252 @@ impl IndexBuffer {
     }
 }
 
+/// Triangle faces discarded by the rasterizer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CullMode {
+    #[default]
+    Back,
+    Front,
+    /// Draw both faces, shows the inside of the geometry and the wrongly wound triangles
+    None,
+}
+
+impl CullMode {
+    /// Face culled by the pipeline primitive state.
+    pub fn face(self) -> Option<wgpu::Face> {
+        match self {
+            Self::Back => Some(wgpu::Face::Back),
+            Self::Front => Some(wgpu::Face::Front),
+            Self::None => None,
+        }
+    }
+
+    /// Following mode in the `Back`, `Front`, `None` cycle.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Back => Self::Front,
+            Self::Front => Self::None,
+            Self::None => Self::Back,
+        }
+    }
+}
+
+/// Indexed geometry, a vertex buffer drawn through an index buffer.
+pub struct Mesh {
+    pub vertices: VertexBuffer,
+    pub indices: IndexBuffer,
+}
+
+impl Mesh {
+    pub fn new(vertices: VertexBuffer, indices: IndexBuffer) -> Self {
+        Self { vertices, indices }
+    }
+}
+
+/// Single indexed draw of a mesh, issues the render pass calls in the required order.
+///
+/// Bind groups are bound to consecutive slots starting at 0, in the order they were added.
+pub struct DrawCommand<'a> {
+    pipeline: &'a wgpu::RenderPipeline,
+    bind_groups: Vec<&'a wgpu::BindGroup>,
+    mesh: &'a Mesh,
+    instances: std::ops::Range<u32>,
+}
+
+impl<'a> DrawCommand<'a> {
+    /// Draw a single instance of the mesh with the pipeline.
+    pub fn new(pipeline: &'a wgpu::RenderPipeline, mesh: &'a Mesh) -> Self {
+        Self {
+            pipeline,
+            bind_groups: Vec::new(),
+            mesh,
+            instances: 0..1,
+        }
+    }
+
+    /// Bind the group to the next bind group slot.
+    pub fn bind_group(mut self, bind_group: &'a wgpu::BindGroup) -> Self {
+        self.bind_groups.push(bind_group);
+        self
+    }
+
+    pub fn instances(mut self, instances: std::ops::Range<u32>) -> Self {
+        self.instances = instances;
+        self
+    }
+
+    /// Record the pipeline, bind groups, buffers and the draw call into the render pass.
+    pub fn execute(&self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(self.pipeline);
+        for (index, bind_group) in self.bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(index as u32, bind_group, &[]);
+        }
+        render_pass.set_vertex_buffer(0, self.mesh.vertices.buffer().slice(..));
+        render_pass.set_index_buffer(
+            self.mesh.indices.buffer().slice(..),
+            self.mesh.indices.format(),
+        );
+        render_pass.draw_indexed(0..self.mesh.indices.count(), 0, self.instances.clone());
+    }
+}
+
+/// Block until the submitted GPU work completes, firing the pending buffer map callbacks.
+pub fn poll_wait(device: &wgpu::Device) {
+    device.poll(wgpu::Maintain::Wait);
+}
+
+/// Fire the callbacks of the GPU work completed so far without blocking.
+///
+/// Returns `true` when no submitted work is pending.
+pub fn poll(device: &wgpu::Device) -> bool {
+    device.poll(wgpu::Maintain::Poll)
+}
+
+/// Read back the content of a GPU buffer, blocking until the copy finishes.
+///
+/// The buffer must have been created with [`wgpu::BufferUsages::COPY_SRC`].
+pub fn read_buffer<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+) -> Vec<T> {
+    let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback staging buffer"),
+        size: buffer.size(),
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback encoder"),
+    });
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging_buffer, 0, buffer.size());
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = staging_buffer.slice(..);
+    map_read_blocking(device, &slice);
+
+    let data = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+    staging_buffer.unmap();
+    data
+}
+
+/// Copy the texture content into an image, blocking until the copy finishes.
+///
+/// Multisampled textures can not be copied, they are resolved to a single sample first
+/// and need the [`wgpu::TextureUsages::RENDER_ATTACHMENT`] usage. Single sampled textures
+/// need [`wgpu::TextureUsages::COPY_SRC`]. Returns `None` for formats other than 8 bit
+/// RGBA and BGRA.
+pub fn capture_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Option<RgbaImage> {
+    let format = texture.format();
+    let bgra = match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        _ => return None,
+    };
+    let size = wgpu::Extent3d {
+        depth_or_array_layers: 1,
+        ..texture.size()
+    };
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Capture encoder"),
+    });
+
+    let resolved;
+    let source = if texture.sample_count() > 1 {
+        resolved = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture resolve target"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let resolve_view = resolved.create_view(&wgpu::TextureViewDescriptor::default());
+        // An empty pass keeping the content resolves it into the single sampled target.
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Capture resolve pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: Some(&resolve_view),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        &resolved
+    } else {
+        texture
+    };
+
+    // Buffer rows must be aligned, the padding is dropped when building the image.
+    let row_size = size.width * 4;
+    let padded_row_size =
+        row_size.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Capture staging buffer"),
+        size: padded_row_size as u64 * size.height as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        source.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &staging_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_size),
+                rows_per_image: None,
+            },
+        },
+        size,
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = staging_buffer.slice(..);
+    map_read_blocking(device, &slice);
+
+    let mut image = RgbaImage::new(size.width, size.height);
+    {
+        let data = slice.get_mapped_range();
+        for (row, padded_row) in image
+            .chunks_exact_mut(row_size as usize)
+            .zip(data.chunks_exact(padded_row_size as usize))
+        {
+            row.copy_from_slice(&padded_row[..row_size as usize]);
+        }
+    }
+    staging_buffer.unmap();
+
+    if bgra {
+        image.pixels_mut().for_each(|pixel| pixel.0.swap(0, 2));
+    }
+    Some(image)
+}
+
+/// Map the buffer slice for reading, blocking until the mapping completes.
+fn map_read_blocking(device: &wgpu::Device, slice: &wgpu::BufferSlice) {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    poll_wait(device);
+    receiver
+        .recv()
+        .expect("Buffer map callback was not called")
+        .expect("Failed to map the readback buffer");
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
@@ -156,3 +428,392 @@ pub const QUAD_VERTICES: &[Vertex] = &[
 ];
 
 pub const QUAD_INDICES: &[u16] = &[0, 1, 2, 3, 0, 2];
+/// Initial cull mode of the display quad, both faces are drawn so the traced image stays
+/// visible whatever the winding of the quad
+pub const QUAD_CULL_MODE: CullMode = CullMode::None;
+
+/// Counter-clockwise triangle list state of the mesh pipelines.
+pub fn triangle_primitive_state(
+    polygon_mode: wgpu::PolygonMode,
+    cull_mode: CullMode,
+) -> wgpu::PrimitiveState {
+    wgpu::PrimitiveState {
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        strip_index_format: None,
+        front_face: wgpu::FrontFace::Ccw,
+        cull_mode: cull_mode.face(),
+        polygon_mode,
+        unclipped_depth: false,
+        conservative: false,
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl LineVertex {
+    pub fn layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Initial number of vertices of the debug line buffer
+const DEBUG_LINES_INITIAL_CAPACITY: usize = 1024;
+
+/// Line list built on the CPU, two vertices per line.
+#[derive(Debug, Default)]
+pub struct LineBuilder {
+    vertices: Vec<LineVertex>,
+}
+
+impl LineBuilder {
+    pub fn draw_line(&mut self, start: glam::Vec3, end: glam::Vec3, color: glam::Vec4) {
+        let color = color.to_array();
+        self.vertices.push(LineVertex {
+            position: start.to_array(),
+            color,
+        });
+        self.vertices.push(LineVertex {
+            position: end.to_array(),
+            color,
+        });
+    }
+
+    /// Draw the twelve edges of the box.
+    pub fn draw_aabb(&mut self, aabb: &Aabb, color: glam::Vec4) {
+        let corner = |i: u32| {
+            glam::Vec3::new(
+                if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            )
+        };
+        // Connect every corner to the corners differing by a single axis.
+        for i in 0..8 {
+            for axis in [1, 2, 4] {
+                if i & axis == 0 {
+                    self.draw_line(corner(i), corner(i | axis), color);
+                }
+            }
+        }
+    }
+
+    /// Draw the ray from its origin up to `length` along its direction.
+    pub fn draw_ray(&mut self, ray: &Ray, length: f32, color: glam::Vec4) {
+        self.draw_line(ray.origin, ray.at(length), color);
+    }
+
+    /// Draw a grid on the XZ plane centered at the origin, with lines every `spacing` units
+    /// up to `extent` units away from the origin along each axis.
+    pub fn draw_grid(&mut self, spacing: f32, extent: f32, color: glam::Vec4) {
+        if spacing <= 0.0 {
+            return;
+        }
+
+        let lines = (extent / spacing).floor() as i32;
+        let extent = lines as f32 * spacing;
+        for i in -lines..=lines {
+            let offset = i as f32 * spacing;
+            self.draw_line(
+                glam::Vec3::new(offset, 0.0, -extent),
+                glam::Vec3::new(offset, 0.0, extent),
+                color,
+            );
+            self.draw_line(
+                glam::Vec3::new(-extent, 0.0, offset),
+                glam::Vec3::new(extent, 0.0, offset),
+                color,
+            );
+        }
+    }
+
+    /// Draw the X (red), Y (green) and Z (blue) axes from the origin.
+    pub fn draw_axes(&mut self, length: f32) {
+        for axis in [glam::Vec3::X, glam::Vec3::Y, glam::Vec3::Z] {
+            self.draw_line(glam::Vec3::ZERO, axis * length, axis.extend(1.0));
+        }
+    }
+
+    /// Lines queued since the last clear.
+    pub fn vertices(&self) -> &[LineVertex] {
+        &self.vertices
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+/// Immediate mode line renderer for debug visualizations.
+///
+/// Lines are queued every frame in [`DebugLines::lines`], uploaded by
+/// [`DebugLines::prepare`] and drawn by [`DebugLines::render`].
+pub struct DebugLines {
+    pub lines: LineBuilder,
+    /// Number of vertices uploaded by the last prepare
+    vertex_count: u32,
+    vertex_buffer: wgpu::Buffer,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl DebugLines {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Debug lines shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                shader::load_embedded("debug_lines.wgsl")
+                    .unwrap_or_else(|err| panic!("Invalid shader debug_lines.wgsl: {}", err))
+                    .into(),
+            ),
+        });
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Debug lines camera buffer"),
+            contents: bytemuck::cast_slice(&[glam::Mat4::IDENTITY]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("Debug lines camera bind group layout"),
+            });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+            label: Some("Debug lines camera bind group"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug lines pipeline layout"),
+            bind_group_layouts: &[&camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Debug lines pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[LineVertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        Self {
+            lines: LineBuilder::default(),
+            vertex_count: 0,
+            vertex_buffer: create_line_buffer(device, DEBUG_LINES_INITIAL_CAPACITY),
+            camera_buffer,
+            camera_bind_group,
+            pipeline,
+        }
+    }
+
+    /// Upload the queued lines and the camera transform, clearing the queue for the next frame.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view_projection: glam::Mat4,
+    ) {
+        let vertices = self.lines.vertices();
+        let required_size = std::mem::size_of_val(vertices) as u64;
+        if required_size > self.vertex_buffer.size() {
+            self.vertex_buffer = create_line_buffer(device, vertices.len().next_power_of_two());
+        }
+
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(vertices));
+        queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[view_projection]),
+        );
+        self.vertex_count = vertices.len() as u32;
+        self.lines.clear();
+    }
+
+    /// Draw the lines uploaded by the last prepare.
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.vertex_count == 0 {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+fn create_line_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Debug lines vertex buffer"),
+        size: (capacity * std::mem::size_of::<LineVertex>()) as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Device for the tests that need a GPU, they are ignored by default and run with
+/// `cargo test -- --ignored` on a machine with an adapter.
+#[cfg(test)]
+pub(crate) fn test_device() -> (wgpu::Device, wgpu::Queue) {
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::LowPower,
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    }))
+    .expect("no GPU adapter available");
+    pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))
+        .expect("failed to request the test device")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_quad_draws_both_faces() {
+        let primitive = triangle_primitive_state(wgpu::PolygonMode::Fill, QUAD_CULL_MODE);
+        assert_eq!(primitive.cull_mode, None);
+        assert_eq!(
+            triangle_primitive_state(wgpu::PolygonMode::Fill, CullMode::default()).cull_mode,
+            Some(wgpu::Face::Back)
+        );
+    }
+
+    #[test]
+    fn two_lines_are_four_vertices() {
+        let mut lines = LineBuilder::default();
+        lines.draw_line(glam::Vec3::ZERO, glam::Vec3::ONE, glam::Vec4::ONE);
+        lines.draw_line(glam::Vec3::X, glam::Vec3::Y, glam::Vec4::W);
+
+        let positions: Vec<_> = lines.vertices().iter().map(|v| v.position).collect();
+        assert_eq!(
+            positions,
+            [[0.0; 3], [1.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+        );
+        assert_eq!(lines.vertices()[3].color, [0.0, 0.0, 0.0, 1.0]);
+
+        lines.clear();
+        assert!(lines.vertices().is_empty());
+    }
+
+    #[test]
+    fn aabb_draws_the_twelve_edges() {
+        let mut lines = LineBuilder::default();
+        let aabb = Aabb {
+            min: glam::Vec3::ZERO,
+            max: glam::Vec3::ONE,
+        };
+        lines.draw_aabb(&aabb, glam::Vec4::ONE);
+
+        let vertices = lines.vertices();
+        assert_eq!(vertices.len(), 24);
+        for edge in vertices.chunks(2) {
+            let start = glam::Vec3::from(edge[0].position);
+            let end = glam::Vec3::from(edge[1].position);
+            // Every edge runs along a single axis of the unit box.
+            assert_eq!((end - start).length(), 1.0);
+        }
+    }
+
+    #[test]
+    fn grid_covers_the_extent() {
+        let mut lines = LineBuilder::default();
+        lines.draw_grid(1.0, 2.5, glam::Vec4::ONE);
+        // Five lines along each axis, from -2 to 2.
+        assert_eq!(lines.vertices().len(), 20);
+        assert_eq!(lines.vertices()[0].position, [-2.0, 0.0, -2.0]);
+
+        lines.draw_grid(0.0, 2.5, glam::Vec4::ONE);
+        assert_eq!(lines.vertices().len(), 20);
+    }
+
+    #[test]
+    fn axes_are_colored_by_axis() {
+        let mut lines = LineBuilder::default();
+        lines.draw_axes(2.0);
+
+        let vertices = lines.vertices();
+        assert_eq!(vertices.len(), 6);
+        assert_eq!(vertices[1].position, [2.0, 0.0, 0.0]);
+        assert_eq!(vertices[5].color, [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn prepare_grows_the_buffer_and_clears_the_queue() {
+        let (device, queue) = test_device();
+        let mut debug_lines = DebugLines::new(&device, wgpu::TextureFormat::Rgba8Unorm, 1);
+        for _ in 0..DEBUG_LINES_INITIAL_CAPACITY {
+            debug_lines
+                .lines
+                .draw_line(glam::Vec3::ZERO, glam::Vec3::ONE, glam::Vec4::ONE);
+        }
+
+        debug_lines.prepare(&device, &queue, glam::Mat4::IDENTITY);
+        assert_eq!(
+            debug_lines.vertex_count,
+            2 * DEBUG_LINES_INITIAL_CAPACITY as u32
+        );
+        assert!(
+            debug_lines.vertex_buffer.size()
+                >= (2 * DEBUG_LINES_INITIAL_CAPACITY * std::mem::size_of::<LineVertex>()) as u64
+        );
+        assert!(debug_lines.lines.vertices().is_empty());
+    }
+}
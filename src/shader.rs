@@ -0,0 +1,311 @@
+use std::{fmt, path::Path};
+
+/// Shaders embedded in the binary, referenced by their file name.
+const EMBEDDED_SHADERS: &[(&str, &str)] = &[
+    (
+        "basic_shape.wgsl",
+        include_str!("asset/shader/basic_shape.wgsl"),
+    ),
+    ("camera.wgsl", include_str!("asset/shader/camera.wgsl")),
+    (
+        "debug_lines.wgsl",
+        include_str!("asset/shader/debug_lines.wgsl"),
+    ),
+    (
+        "text_overlay.wgsl",
+        include_str!("asset/shader/text_overlay.wgsl"),
+    ),
+];
+
+/// Directive replaced by the content of the quoted shader, `//!include "camera.wgsl"`
+const INCLUDE_DIRECTIVE: &str = "//!include";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncludeError {
+    /// The shader could not be loaded
+    NotFound(String),
+    /// The shader includes itself, through the listed chain of includes
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(name) => write!(f, "shader {:?} not found", name),
+            Self::Cycle(chain) => write!(f, "cyclic shader include: {}", chain.join(" -> ")),
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+/// Source of a shader embedded in the binary.
+pub fn embedded_source(name: &str) -> Option<String> {
+    EMBEDDED_SHADERS
+        .iter()
+        .find(|(embedded, _)| *embedded == name)
+        .map(|(_, source)| source.to_string())
+}
+
+/// Load an embedded shader, resolving its includes from the other embedded shaders.
+pub fn load_embedded(name: &str) -> Result<String, IncludeError> {
+    preprocess(name, embedded_source)
+}
+
+/// Load a shader file, resolving its includes relative to the file directory.
+pub fn load_file(path: &Path) -> Result<String, IncludeError> {
+    let directory = path.parent().unwrap_or(Path::new("."));
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    preprocess(&name, |name| {
+        std::fs::read_to_string(directory.join(name)).ok()
+    })
+}
+
+/// Return the source of the shader `name` with every include directive replaced by the
+/// included shader source, loaded by `load`.
+///
+/// Shaders included more than once are inlined every time.
+pub fn preprocess(
+    name: &str,
+    load: impl Fn(&str) -> Option<String>,
+) -> Result<String, IncludeError> {
+    let mut output = String::new();
+    expand(name, &load, &mut Vec::new(), &mut output)?;
+    Ok(output)
+}
+
+fn expand(
+    name: &str,
+    load: &dyn Fn(&str) -> Option<String>,
+    stack: &mut Vec<String>,
+    output: &mut String,
+) -> Result<(), IncludeError> {
+    if stack.iter().any(|included| included == name) {
+        let mut chain = stack.clone();
+        chain.push(name.to_owned());
+        return Err(IncludeError::Cycle(chain));
+    }
+
+    let source = load(name).ok_or_else(|| IncludeError::NotFound(name.to_owned()))?;
+    stack.push(name.to_owned());
+    for line in source.lines() {
+        match parse_include(line) {
+            Some(included) => expand(included, load, stack, output)?,
+            None => {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+    }
+    stack.pop();
+    Ok(())
+}
+
+/// Name of the shader included by the line, `None` if the line is not an include directive.
+fn parse_include(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix(INCLUDE_DIRECTIVE)?
+        .trim()
+        .strip_prefix('"')?
+        .strip_suffix('"')
+}
+
+#[cfg(feature = "hot-reload")]
+pub use watcher::ShaderWatcher;
+
+#[cfg(feature = "hot-reload")]
+mod watcher {
+    use std::{
+        path::{Path, PathBuf},
+        sync::mpsc::{channel, Receiver},
+    };
+
+    use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+    /// Watch a shader file on disk, reporting its new source when it changes.
+    pub struct ShaderWatcher {
+        path: PathBuf,
+        receiver: Receiver<notify::Result<notify::Event>>,
+        /// File system watcher feeding the receiver, `None` when the events come from elsewhere
+        _watcher: Option<RecommendedWatcher>,
+    }
+
+    impl ShaderWatcher {
+        pub fn new(path: impl Into<PathBuf>) -> notify::Result<Self> {
+            let path = path.into();
+            let (sender, receiver) = channel();
+            let mut watcher = notify::recommended_watcher(sender)?;
+
+            // Editors usually replace the file on save, which drops a watch placed on the
+            // file itself, so the parent directory is watched instead.
+            let directory = path.parent().unwrap_or(Path::new("."));
+            watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+            Ok(Self {
+                _watcher: Some(watcher),
+                ..Self::from_receiver(path, receiver)
+            })
+        }
+
+        /// Report the changes of the file from the events sent to `receiver` instead of
+        /// watching the file system.
+        pub fn from_receiver(
+            path: impl Into<PathBuf>,
+            receiver: Receiver<notify::Result<notify::Event>>,
+        ) -> Self {
+            Self {
+                path: path.into(),
+                receiver,
+                _watcher: None,
+            }
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Return the new shader source if the file changed since the last call.
+        pub fn poll_changed(&self) -> Option<String> {
+            let changes = self
+                .receiver
+                .try_iter()
+                .filter(|event| match event {
+                    Ok(event) => {
+                        (event.kind.is_modify() || event.kind.is_create())
+                            && event
+                                .paths
+                                .iter()
+                                .any(|path| path.file_name() == self.path.file_name())
+                    }
+                    Err(err) => {
+                        tracing::warn!("shader watcher error: {}", err);
+                        false
+                    }
+                })
+                .count();
+
+            if changes == 0 {
+                return None;
+            }
+
+            match std::fs::read_to_string(&self.path) {
+                Ok(source) => Some(source),
+                Err(err) => {
+                    tracing::warn!("failed to read shader {:?}: {}", self.path, err);
+                    None
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::mpsc::channel;
+
+        use notify::{event::ModifyKind, Event, EventKind};
+
+        use super::*;
+
+        fn modified(path: &Path) -> notify::Result<Event> {
+            Ok(Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path.to_path_buf()))
+        }
+
+        #[test]
+        fn change_event_reports_the_new_source() {
+            let path = std::env::temp_dir().join(format!("shader_{}.wgsl", std::process::id()));
+            std::fs::write(&path, "// new source").unwrap();
+            let (sender, receiver) = channel();
+            let watcher = ShaderWatcher::from_receiver(&path, receiver);
+            assert_eq!(watcher.poll_changed(), None);
+
+            sender.send(modified(&path)).unwrap();
+            assert_eq!(watcher.poll_changed().as_deref(), Some("// new source"));
+            assert_eq!(watcher.poll_changed(), None);
+
+            // Other files of the watched directory are ignored.
+            sender
+                .send(modified(&path.with_file_name("other.wgsl")))
+                .unwrap();
+            assert_eq!(watcher.poll_changed(), None);
+
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources<'a>(shaders: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            shaders
+                .iter()
+                .find(|(shader, _)| *shader == name)
+                .map(|(_, source)| source.to_string())
+        }
+    }
+
+    #[test]
+    fn include_is_replaced_by_the_shader_source() {
+        let load = sources(&[
+            ("main.wgsl", "//!include \"common.wgsl\"\nfn main() {}"),
+            (
+                "common.wgsl",
+                "  //!include  \"consts.wgsl\"  \nfn common() {}",
+            ),
+            ("consts.wgsl", "const PI = 3.14;"),
+        ]);
+        assert_eq!(
+            preprocess("main.wgsl", load).unwrap(),
+            "const PI = 3.14;\nfn common() {}\nfn main() {}\n"
+        );
+    }
+
+    #[test]
+    fn missing_include_is_not_found() {
+        let load = sources(&[("main.wgsl", "//!include \"missing.wgsl\"")]);
+        assert_eq!(
+            preprocess("main.wgsl", load),
+            Err(IncludeError::NotFound("missing.wgsl".to_owned()))
+        );
+    }
+
+    #[test]
+    fn cyclic_include_reports_the_chain() {
+        let load = sources(&[
+            ("a.wgsl", "//!include \"b.wgsl\""),
+            ("b.wgsl", "//!include \"a.wgsl\""),
+        ]);
+        assert_eq!(
+            preprocess("a.wgsl", load),
+            Err(IncludeError::Cycle(vec![
+                "a.wgsl".to_owned(),
+                "b.wgsl".to_owned(),
+                "a.wgsl".to_owned(),
+            ]))
+        );
+    }
+
+    #[test]
+    fn shader_included_twice_is_not_a_cycle() {
+        let load = sources(&[
+            ("main.wgsl", "//!include \"a.wgsl\"\n//!include \"a.wgsl\""),
+            ("a.wgsl", "fn a() {}"),
+        ]);
+        assert_eq!(
+            preprocess("main.wgsl", load).unwrap(),
+            "fn a() {}\nfn a() {}\n"
+        );
+    }
+
+    #[test]
+    fn embedded_shaders_resolve() {
+        for (name, _) in EMBEDDED_SHADERS {
+            assert!(load_embedded(name).is_ok(), "{}", name);
+        }
+    }
+}
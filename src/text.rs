@@ -0,0 +1,477 @@
+use image::{Rgba, RgbaImage};
+use wgpu::util::DeviceExt;
+
+use crate::{shader, texture::Texture};
+
+/// Glyph bitmap size in font pixels
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// Glyph cell size in the atlas, the extra column and row separate the characters
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+/// Screen pixels per font pixel
+const TEXT_SCALE: f32 = 2.0;
+const TEXT_COLOR: glam::Vec4 = glam::Vec4::ONE;
+/// Character drawn in place of the characters missing from the font
+const FALLBACK_CHAR: char = '?';
+/// Initial number of glyphs of the text vertex buffer
+const TEXT_INITIAL_CAPACITY: usize = 256;
+const VERTICES_PER_GLYPH: usize = 6;
+
+/// 5x7 bitmap font, one row per byte from top to bottom, bit 4 is the left column.
+///
+/// Lowercase letters are drawn with the uppercase glyphs.
+#[rustfmt::skip]
+const GLYPHS: &[(char, [u8; 7])] = &[
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('/', [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+    ('[', [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110]),
+    (']', [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110]),
+    ('<', [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010]),
+    ('>', [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000]),
+    ('#', [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010]),
+    ('*', [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000]),
+    ('_', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+];
+
+/// Index of the character glyph in the font atlas, `None` for whitespace.
+fn glyph_index(character: char) -> Option<usize> {
+    if character.is_whitespace() {
+        return None;
+    }
+    let character = character.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .position(|(glyph, _)| *glyph == character)
+        .or_else(|| GLYPHS.iter().position(|(glyph, _)| *glyph == FALLBACK_CHAR))
+}
+
+/// Render the font glyphs side by side, white with the coverage in the alpha channel.
+fn font_atlas() -> RgbaImage {
+    let mut atlas = RgbaImage::new(GLYPHS.len() as u32 * CELL_WIDTH, CELL_HEIGHT);
+    for (index, (_, rows)) in GLYPHS.iter().enumerate() {
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                if row >> (GLYPH_WIDTH - 1 - x) & 1 == 1 {
+                    atlas.put_pixel(
+                        index as u32 * CELL_WIDTH + x,
+                        y as u32,
+                        Rgba([255, 255, 255, 255]),
+                    );
+                }
+            }
+        }
+    }
+    atlas
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlyphVertex {
+    /// Position in pixels from the top left corner of the target
+    pub position: [f32; 2],
+    pub tex_coords: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl GlyphVertex {
+    pub fn layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GlyphVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress * 2,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Glyph quads built on the CPU, six vertices per glyph.
+#[derive(Debug, Default)]
+pub struct TextBuilder {
+    vertices: Vec<GlyphVertex>,
+}
+
+impl TextBuilder {
+    /// Queue the text with its top left corner at `x`, `y` pixels from the top left corner
+    /// of the target, `\n` starts a new line.
+    pub fn draw_text(&mut self, x: f32, y: f32, text: &str) {
+        self.draw_text_colored(x, y, text, TEXT_COLOR);
+    }
+
+    pub fn draw_text_colored(&mut self, x: f32, y: f32, text: &str, color: glam::Vec4) {
+        let advance = glam::Vec2::new(CELL_WIDTH as f32, CELL_HEIGHT as f32) * TEXT_SCALE;
+        let size = glam::Vec2::new(GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32) * TEXT_SCALE;
+        let atlas_width = (GLYPHS.len() as u32 * CELL_WIDTH) as f32;
+        let color = color.to_array();
+
+        for (line, text) in text.lines().enumerate() {
+            let top = y + line as f32 * advance.y;
+            for (column, character) in text.chars().enumerate() {
+                let Some(index) = glyph_index(character) else {
+                    continue;
+                };
+
+                let min = glam::Vec2::new(x + column as f32 * advance.x, top);
+                let max = min + size;
+                let u_min = (index as u32 * CELL_WIDTH) as f32 / atlas_width;
+                let u_max = u_min + GLYPH_WIDTH as f32 / atlas_width;
+                let v_max = GLYPH_HEIGHT as f32 / CELL_HEIGHT as f32;
+                let vertex = |x: f32, y: f32, u: f32, v: f32| GlyphVertex {
+                    position: [x, y],
+                    tex_coords: [u, v],
+                    color,
+                };
+
+                self.vertices.extend_from_slice(&[
+                    vertex(min.x, min.y, u_min, 0.0),
+                    vertex(min.x, max.y, u_min, v_max),
+                    vertex(max.x, max.y, u_max, v_max),
+                    vertex(min.x, min.y, u_min, 0.0),
+                    vertex(max.x, max.y, u_max, v_max),
+                    vertex(max.x, min.y, u_max, 0.0),
+                ]);
+            }
+        }
+    }
+
+    /// Glyphs queued since the last clear, six vertices per glyph quad.
+    pub fn vertices(&self) -> &[GlyphVertex] {
+        &self.vertices
+    }
+
+    /// Number of glyph quads queued since the last clear.
+    pub fn glyph_count(&self) -> usize {
+        self.vertices.len() / VERTICES_PER_GLYPH
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+/// Immediate mode bitmap text renderer for debug information drawn over the frame.
+///
+/// Text is queued every frame in [`TextOverlay::text`], uploaded by [`TextOverlay::prepare`]
+/// and drawn alpha blended by [`TextOverlay::render`], one quad per glyph.
+pub struct TextOverlay {
+    pub text: TextBuilder,
+    /// Number of vertices uploaded by the last prepare
+    vertex_count: u32,
+    vertex_buffer: wgpu::Buffer,
+    screen_buffer: wgpu::Buffer,
+    screen_bind_group: wgpu::BindGroup,
+    font_bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl TextOverlay {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Text overlay shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                shader::load_embedded("text_overlay.wgsl")
+                    .unwrap_or_else(|err| panic!("Invalid shader text_overlay.wgsl: {}", err))
+                    .into(),
+            ),
+        });
+
+        let screen_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text overlay screen buffer"),
+            contents: bytemuck::cast_slice(&[glam::Vec4::ONE]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let screen_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("Text overlay screen bind group layout"),
+            });
+        let screen_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &screen_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: screen_buffer.as_entire_binding(),
+            }],
+            label: Some("Text overlay screen bind group"),
+        });
+
+        let atlas = font_atlas();
+        let mut font = Texture::from_image(
+            device,
+            queue,
+            &atlas,
+            atlas.width(),
+            atlas.height(),
+            Some("Text overlay font atlas"),
+        );
+        font.set_filter(device, wgpu::FilterMode::Nearest);
+        let font_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("Text overlay font bind group layout"),
+            });
+        let font_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &font_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&font.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&font.sampler),
+                },
+            ],
+            label: Some("Text overlay font bind group"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text overlay pipeline layout"),
+            bind_group_layouts: &[&screen_bind_group_layout, &font_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Text overlay pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[GlyphVertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            text: TextBuilder::default(),
+            vertex_count: 0,
+            vertex_buffer: create_glyph_buffer(device, TEXT_INITIAL_CAPACITY),
+            screen_buffer,
+            screen_bind_group,
+            font_bind_group,
+            pipeline,
+        }
+    }
+
+    /// Upload the queued text and the target size in pixels, clearing the queue for the
+    /// next frame.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, width: u32, height: u32) {
+        let vertices = self.text.vertices();
+        let required_size = std::mem::size_of_val(vertices) as u64;
+        if required_size > self.vertex_buffer.size() {
+            self.vertex_buffer =
+                create_glyph_buffer(device, self.text.glyph_count().next_power_of_two());
+        }
+
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(vertices));
+        queue.write_buffer(
+            &self.screen_buffer,
+            0,
+            bytemuck::cast_slice(&[glam::Vec4::new(width as f32, height as f32, 0.0, 0.0)]),
+        );
+        self.vertex_count = vertices.len() as u32;
+        self.text.clear();
+    }
+
+    /// Draw the text uploaded by the last prepare, in a single sampled pass over the frame.
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.vertex_count == 0 {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.screen_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.font_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+fn create_glyph_buffer(device: &wgpu::Device, glyphs: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Text overlay vertex buffer"),
+        size: (glyphs * VERTICES_PER_GLYPH * std::mem::size_of::<GlyphVertex>()) as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::test_device;
+
+    #[test]
+    fn glyph_is_a_quad_at_the_pen_position() {
+        let mut text = TextBuilder::default();
+        text.draw_text(10.0, 20.0, "A");
+        assert_eq!(text.glyph_count(), 1);
+
+        let positions: Vec<_> = text.vertices().iter().map(|v| v.position).collect();
+        let right = 10.0 + GLYPH_WIDTH as f32 * TEXT_SCALE;
+        let bottom = 20.0 + GLYPH_HEIGHT as f32 * TEXT_SCALE;
+        assert_eq!(
+            positions,
+            [
+                [10.0, 20.0],
+                [10.0, bottom],
+                [right, bottom],
+                [10.0, 20.0],
+                [right, bottom],
+                [right, 20.0],
+            ]
+        );
+    }
+
+    #[test]
+    fn three_characters_are_three_advancing_quads() {
+        let mut text = TextBuilder::default();
+        text.draw_text(0.0, 0.0, "abc");
+        assert_eq!(text.glyph_count(), 3);
+
+        let left: Vec<_> = text
+            .vertices()
+            .chunks(VERTICES_PER_GLYPH)
+            .map(|quad| quad[0].position[0])
+            .collect();
+        assert!(left[0] < left[1] && left[1] < left[2], "{:?}", left);
+    }
+
+    #[test]
+    fn whitespace_advances_without_a_glyph() {
+        let mut text = TextBuilder::default();
+        text.draw_text(0.0, 0.0, "a b\nc");
+        assert_eq!(text.glyph_count(), 3);
+
+        let origin = |glyph: usize| text.vertices()[glyph * VERTICES_PER_GLYPH].position;
+        let advance_x = CELL_WIDTH as f32 * TEXT_SCALE;
+        let advance_y = CELL_HEIGHT as f32 * TEXT_SCALE;
+        assert_eq!(origin(1), [2.0 * advance_x, 0.0]);
+        assert_eq!(origin(2), [0.0, advance_y]);
+    }
+
+    #[test]
+    fn missing_characters_use_the_fallback_glyph() {
+        assert_eq!(glyph_index('a'), glyph_index('A'));
+        assert_eq!(glyph_index('~'), glyph_index(FALLBACK_CHAR));
+        assert_eq!(glyph_index(' '), None);
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn prepare_grows_the_buffer_and_clears_the_queue() {
+        let (device, queue) = test_device();
+        let mut overlay = TextOverlay::new(&device, &queue, wgpu::TextureFormat::Rgba8Unorm);
+        overlay
+            .text
+            .draw_text(0.0, 0.0, &"A".repeat(TEXT_INITIAL_CAPACITY + 1));
+
+        overlay.prepare(&device, &queue, 800, 600);
+        assert_eq!(overlay.text.glyph_count(), 0);
+        assert_eq!(
+            overlay.vertex_count as usize,
+            (TEXT_INITIAL_CAPACITY + 1) * VERTICES_PER_GLYPH
+        );
+        assert!(
+            overlay.vertex_buffer.size()
+                >= overlay.vertex_count as u64 * std::mem::size_of::<GlyphVertex>() as u64
+        );
+    }
+}
@@ -1,9 +1,13 @@
-use image::{ImageBuffer, Rgba};
+use image::{DynamicImage, ImageBuffer, Rgba};
 
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    filter: wgpu::FilterMode,
 }
 
 impl Texture {
@@ -20,14 +24,17 @@ impl Texture {
             height,
             depth_or_array_layers: 1,
         };
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size,
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[],
         });
 
@@ -38,7 +45,7 @@ impl Texture {
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
             },
-            &buf,
+            buf,
             wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * width),
@@ -48,23 +55,106 @@ impl Texture {
         );
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        let filter = wgpu::FilterMode::Linear;
+        let sampler = create_sampler(device, filter);
 
         Self {
             texture,
             view,
             sampler,
+            width,
+            height,
+            format,
+            filter,
         }
     }
 
+    /// Create the texture from an image of any color type, converted by [`to_rgba8`].
+    pub fn from_dynamic_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &DynamicImage,
+        label: Option<&str>,
+    ) -> Self {
+        let rgba = to_rgba8(image);
+        Self::from_image(device, queue, &rgba, rgba.width(), rgba.height(), label)
+    }
+
+    /// Width and height of the texture in texels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Filter used when the texture is magnified or minified.
+    pub fn filter(&self) -> wgpu::FilterMode {
+        self.filter
+    }
+
+    /// Recreate the sampler with a different magnification and minification filter, bind
+    /// groups using the previous sampler must be recreated.
+    pub fn set_filter(&mut self, device: &wgpu::Device, filter: wgpu::FilterMode) {
+        self.filter = filter;
+        self.sampler = create_sampler(device, filter);
+    }
+
+    /// Replace a `width` by `height` rectangle at `(x, y)` with tightly packed RGBA8 data.
+    ///
+    /// # Panics
+    ///
+    /// When the region exceeds the texture bounds or the data does not cover the region.
+    pub fn update_region(
+        &self,
+        queue: &wgpu::Queue,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) {
+        assert!(
+            x.checked_add(width)
+                .is_some_and(|right| right <= self.width)
+                && y.checked_add(height)
+                    .is_some_and(|bottom| bottom <= self.height),
+            "region {}x{} at ({}, {}) exceeds the {}x{} texture",
+            width,
+            height,
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        assert_eq!(
+            data.len(),
+            4 * width as usize * height as usize,
+            "region data must contain 4 bytes per texel"
+        );
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+            },
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
     pub fn update_data(
         &self,
         queue: &wgpu::Queue,
@@ -84,7 +174,7 @@ impl Texture {
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
             },
-            &buf,
+            buf,
             wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * width),
@@ -94,3 +184,458 @@ impl Texture {
         );
     }
 }
+
+/// 2D texture array where every layer shares the same size, bound as a single texture.
+pub struct TextureArray {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    width: u32,
+    height: u32,
+    layers: u32,
+}
+
+impl TextureArray {
+    /// Create an array of `layers` empty layers.
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        layers: u32,
+        label: Option<&str>,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let sampler = create_sampler(device, wgpu::FilterMode::Linear);
+
+        Self {
+            texture,
+            view,
+            sampler,
+            width,
+            height,
+            layers,
+        }
+    }
+
+    /// Create an array with one layer per image.
+    ///
+    /// # Panics
+    ///
+    /// When there are no images or they do not share the same dimensions.
+    pub fn from_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: &[ImageBuffer<Rgba<u8>, Vec<u8>>],
+        label: Option<&str>,
+    ) -> Self {
+        assert!(
+            !images.is_empty(),
+            "texture array requires at least one layer"
+        );
+        let (width, height) = images[0].dimensions();
+        let array = Self::new(device, width, height, images.len() as u32, label);
+        for (layer, image) in images.iter().enumerate() {
+            array.update_layer(queue, layer as u32, image);
+        }
+        array
+    }
+
+    /// Replace the content of a layer.
+    ///
+    /// # Panics
+    ///
+    /// When the layer is out of range or the image size differs from the array size.
+    pub fn update_layer(
+        &self,
+        queue: &wgpu::Queue,
+        layer: u32,
+        buf: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    ) {
+        assert!(
+            layer < self.layers,
+            "layer {} out of range, the array has {} layers",
+            layer,
+            self.layers
+        );
+        assert_eq!(
+            buf.dimensions(),
+            (self.width, self.height),
+            "layer size must match the texture array size"
+        );
+
+        write_layer(queue, &self.texture, layer, buf);
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+}
+
+/// Cube texture made of six square faces, ordered +X, -X, +Y, -Y, +Z, -Z.
+pub struct Cubemap {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    size: u32,
+}
+
+impl Cubemap {
+    /// Number of faces of a cube texture
+    pub const FACES: u32 = 6;
+
+    /// # Panics
+    ///
+    /// When the faces are not square or do not share the same size.
+    pub fn from_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[ImageBuffer<Rgba<u8>, Vec<u8>>; 6],
+        label: Option<&str>,
+    ) -> Self {
+        let (size, height) = faces[0].dimensions();
+        assert_eq!(size, height, "cubemap faces must be square");
+        assert!(
+            faces.iter().all(|face| face.dimensions() == (size, size)),
+            "cubemap faces must share the same size"
+        );
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: Self::FACES,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        for (layer, face) in faces.iter().enumerate() {
+            write_layer(queue, &texture, layer as u32, face);
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        // Linear filtering on every axis avoids visible seams between the faces.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+            size,
+        }
+    }
+
+    /// Width and height of each face.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+/// Write the image into an array layer of the texture, the image must cover the whole layer.
+fn write_layer(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    layer: u32,
+    buf: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+) {
+    let (width, height) = buf.dimensions();
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: 0,
+                z: layer,
+            },
+        },
+        buf,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * width),
+            rows_per_image: Some(height),
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+fn create_sampler(device: &wgpu::Device, filter: wgpu::FilterMode) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: filter,
+        min_filter: filter,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    })
+}
+
+/// Convert an image of any color type to RGBA8.
+///
+/// Grayscale channels are replicated to red, green and blue, and a missing alpha
+/// channel is opaque.
+pub fn to_rgba8(image: &DynamicImage) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    image.to_rgba8()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::{read_buffer, test_device};
+
+    /// Copy a layer of the texture back to the CPU as tightly packed RGBA rows.
+    fn read_layer(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        layer: u32,
+    ) -> Vec<u8> {
+        let (width, height) = (texture.width(), texture.height());
+        let row_size = 4 * width;
+        let padded_row_size = row_size.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Texture readback buffer"),
+            size: (padded_row_size * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_size),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let data: Vec<u8> = read_buffer(device, queue, &buffer);
+        data.chunks(padded_row_size as usize)
+            .flat_map(|row| &row[..row_size as usize])
+            .copied()
+            .collect()
+    }
+
+    fn solid(height: u32, color: [u8; 4]) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        ImageBuffer::from_pixel(8, height, Rgba(color))
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn layers_hold_their_own_data() {
+        let (device, queue) = test_device();
+        let array = TextureArray::new(&device, 8, 4, 2, None);
+        let first = solid(4, [255, 0, 0, 255]);
+        let second = solid(4, [0, 0, 255, 128]);
+        array.update_layer(&queue, 0, &first);
+        array.update_layer(&queue, 1, &second);
+
+        assert_eq!(
+            read_layer(&device, &queue, &array.texture, 0),
+            first.into_raw()
+        );
+        assert_eq!(
+            read_layer(&device, &queue, &array.texture, 1),
+            second.into_raw()
+        );
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    #[should_panic(expected = "out of range")]
+    fn layer_out_of_range_is_rejected() {
+        let (device, queue) = test_device();
+        let array = TextureArray::new(&device, 8, 4, 2, None);
+        array.update_layer(&queue, 2, &solid(4, [0; 4]));
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    #[should_panic(expected = "layer size must match")]
+    fn layer_size_mismatch_is_rejected() {
+        let (device, queue) = test_device();
+        let array = TextureArray::new(&device, 8, 4, 2, None);
+        array.update_layer(&queue, 0, &solid(8, [0; 4]));
+    }
+
+    fn faces(size: u32) -> [ImageBuffer<Rgba<u8>, Vec<u8>>; 6] {
+        std::array::from_fn(|face| {
+            ImageBuffer::from_pixel(size, size, Rgba([face as u8 * 40, 0, 0, 255]))
+        })
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn cubemap_has_six_faces_and_a_cube_view() {
+        let (device, queue) = test_device();
+        let cubemap = Cubemap::from_faces(&device, &queue, &faces(8), None);
+
+        assert_eq!(cubemap.texture.depth_or_array_layers(), Cubemap::FACES);
+        assert_eq!(cubemap.size(), 8);
+
+        // Binding the view to a cube texture slot fails the validation for any other view
+        // dimension.
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::Cube,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            }],
+        });
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&cubemap.view),
+            }],
+        });
+        assert!(pollster::block_on(device.pop_error_scope()).is_none());
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    #[should_panic(expected = "must be square")]
+    fn non_square_faces_are_rejected() {
+        let (device, queue) = test_device();
+        let faces = std::array::from_fn(|_| solid(4, [0; 4]));
+        Cubemap::from_faces(&device, &queue, &faces, None);
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    #[should_panic(expected = "must share the same size")]
+    fn unequal_faces_are_rejected() {
+        let (device, queue) = test_device();
+        let mut faces = faces(4);
+        faces[3] = ImageBuffer::from_pixel(8, 8, Rgba([0; 4]));
+        Cubemap::from_faces(&device, &queue, &faces, None);
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn texture_reports_its_size_and_format() {
+        let (device, queue) = test_device();
+        let image = ImageBuffer::from_pixel(800, 800, Rgba([0; 4]));
+        let texture = Texture::from_image(&device, &queue, &image, 800, 800, None);
+
+        assert_eq!(texture.size(), (800, 800));
+        assert_eq!(texture.format(), wgpu::TextureFormat::Rgba8UnormSrgb);
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn region_update_changes_only_the_region() {
+        let (device, queue) = test_device();
+        let image = ImageBuffer::from_pixel(16, 16, Rgba([0, 0, 0, 255]));
+        let texture = Texture::from_image(&device, &queue, &image, 16, 16, None);
+        let white = [255; 4 * 4 * 4];
+        texture.update_region(&queue, 5, 3, 4, 4, &white);
+
+        let data = read_layer(&device, &queue, &texture.texture, 0);
+        for (index, texel) in data.chunks(4).enumerate() {
+            let (x, y) = (index % 16, index / 16);
+            let inside = (5..9).contains(&x) && (3..7).contains(&y);
+            let expected = if inside { [255; 4] } else { [0, 0, 0, 255] };
+            assert_eq!(texel, expected, "texel ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    #[should_panic(expected = "exceeds the 16x16 texture")]
+    fn region_outside_the_texture_is_rejected() {
+        let (device, queue) = test_device();
+        let image = ImageBuffer::from_pixel(16, 16, Rgba([0; 4]));
+        let texture = Texture::from_image(&device, &queue, &image, 16, 16, None);
+        texture.update_region(&queue, 14, 0, 4, 4, &[0; 4 * 4 * 4]);
+    }
+
+    #[test]
+    fn grayscale_is_replicated_and_opaque() {
+        let gray = image::GrayImage::from_fn(4, 1, |x, _| image::Luma([x as u8 * 60]));
+        let rgba = to_rgba8(&DynamicImage::ImageLuma8(gray));
+
+        assert_eq!(rgba.dimensions(), (4, 1));
+        for (x, pixel) in rgba.pixels().enumerate() {
+            let luma = x as u8 * 60;
+            assert_eq!(pixel.0, [luma, luma, luma, 255]);
+        }
+    }
+
+    #[test]
+    fn gray_alpha_keeps_its_alpha() {
+        let gray_alpha = image::GrayAlphaImage::from_pixel(1, 1, image::LumaA([90, 30]));
+        let rgba = to_rgba8(&DynamicImage::ImageLumaA8(gray_alpha));
+        assert_eq!(rgba.get_pixel(0, 0).0, [90, 90, 90, 30]);
+    }
+}
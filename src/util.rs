@@ -2,4 +2,110 @@ pub mod math {
     pub fn degree_to_radian(degree: f32) -> f32 {
         degree * std::f32::consts::PI / 180.0
     }
+
+    /// Replace the NaN and infinite color components with zero.
+    pub fn sanitize_color(color: glam::Vec4) -> glam::Vec4 {
+        let sanitize = |value: f32| if value.is_finite() { value } else { 0.0 };
+        glam::Vec4::new(
+            sanitize(color.x),
+            sanitize(color.y),
+            sanitize(color.z),
+            sanitize(color.w),
+        )
+    }
+
+    /// Element of the Halton low discrepancy sequence in the `[0, 1)` range, `index` starts
+    /// at `1` and `base` must be a prime greater than one.
+    pub fn halton(mut index: u32, base: u32) -> f32 {
+        let mut fraction = 1.0;
+        let mut result = 0.0;
+        while index > 0 {
+            fraction /= base as f32;
+            result += fraction * (index % base) as f32;
+            index /= base;
+        }
+        result
+    }
+}
+
+pub mod rng {
+    /// Small deterministic pseudo random number generator (xorshift64*).
+    ///
+    /// The same seed always produces the same sequence, on every platform.
+    #[derive(Debug, Clone)]
+    pub struct Rng {
+        state: u64,
+    }
+
+    impl Rng {
+        pub fn new(seed: u64) -> Self {
+            // splitmix64 spreads the seed bits, xorshift must never start with a zero state.
+            let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^= z >> 31;
+            Self {
+                state: if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z },
+            }
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+            let mut x = self.state;
+            x ^= x >> 12;
+            x ^= x << 25;
+            x ^= x >> 27;
+            self.state = x;
+            x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        /// Uniform value in the `[0, 1)` range.
+        pub fn next_f32(&mut self) -> f32 {
+            (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+        }
+
+        /// Uniform value in the `[min, max)` range.
+        pub fn range(&mut self, min: f32, max: f32) -> f32 {
+            min + (max - min) * self.next_f32()
+        }
+
+        /// Uniform point inside the box defined by the `min` and `max` corners.
+        pub fn vec3_range(&mut self, min: glam::Vec3, max: glam::Vec3) -> glam::Vec3 {
+            glam::Vec3::new(
+                self.range(min.x, max.x),
+                self.range(min.y, max.y),
+                self.range(min.z, max.z),
+            )
+        }
+
+        /// Uniform point inside the sphere of radius one centered at the origin.
+        pub fn in_unit_sphere(&mut self) -> glam::Vec3 {
+            loop {
+                let point = self.vec3_range(glam::Vec3::NEG_ONE, glam::Vec3::ONE);
+                if point.length_squared() <= 1.0 {
+                    return point;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::math::*;
+
+    #[test]
+    fn sanitize_zeroes_the_non_finite_components() {
+        let color = glam::Vec4::new(f32::NAN, f32::INFINITY, 0.5, f32::NEG_INFINITY);
+        assert_eq!(sanitize_color(color), glam::Vec4::new(0.0, 0.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn halton_radical_inverse() {
+        let base_2: Vec<_> = (1..=4).map(|i| halton(i, 2)).collect();
+        assert_eq!(base_2, [0.5, 0.25, 0.75, 0.125]);
+        let base_3: Vec<_> = (1..=3).map(|i| halton(i, 3)).collect();
+        assert!((base_3[0] - 1.0 / 3.0).abs() < 1e-6);
+        assert!((base_3[1] - 2.0 / 3.0).abs() < 1e-6);
+        assert!((base_3[2] - 1.0 / 9.0).abs() < 1e-6);
+    }
 }